@@ -0,0 +1,141 @@
+use std::future::poll_fn;
+use std::io::{self, BufWriter, Read, Write};
+use std::pin::pin;
+use std::task::Poll;
+
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc;
+use tokio::task;
+
+use crate::ToseConverter;
+
+/// Lines, and chunks of output, in flight between the runtime and the
+/// converter thread
+const CHANNEL_CAPACITY: usize = 64;
+
+impl ToseConverter {
+    /// Convert like [`convert`](Self::convert), reading psql output from and
+    /// writing TOSE to tokio streams, e.g. a child process's stdout
+    ///
+    /// Input lines are handed to the same parser as `convert`, which runs on a
+    /// blocking thread, so neither reading nor converting stalls the runtime.
+    /// Only a bounded number of lines and output chunks are in flight at once;
+    /// output is written as the converter produces it. The converter still
+    /// keeps the parsed rows until the footer, since the schema header states
+    /// the row count, except in summary mode.
+    pub async fn convert_async<R, W>(&self, input: R, mut output: W) -> io::Result<()>
+    where
+        R: AsyncBufRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let (line_sender, lines) = mpsc::channel(CHANNEL_CAPACITY);
+        let (chunk_sender, mut chunks) = mpsc::channel::<Vec<u8>>(CHANNEL_CAPACITY);
+        let converter = self.clone();
+        let conversion = task::spawn_blocking(move || {
+            let input = LineReceiver {
+                lines,
+                line: Vec::new(),
+                pos: 0,
+            };
+            let mut output = BufWriter::new(ChunkSender(chunk_sender));
+            converter.convert(input, &mut output)?;
+            output.flush()
+        });
+
+        let feed = async move {
+            let mut input = input.lines();
+            while let Some(line) = input.next_line().await.transpose() {
+                let failed = line.is_err();
+                // The converter may stop reading before the end of the input
+                if line_sender.send(line).await.is_err() || failed {
+                    break;
+                }
+            }
+        };
+        // Dropping `chunks` on a write error makes the converter stop with
+        // `BrokenPipe` rather than wait for room in the channel
+        let output = &mut output;
+        let drain = async move {
+            while let Some(chunk) = chunks.recv().await {
+                output.write_all(&chunk).await?;
+            }
+            output.flush().await
+        };
+        let written = join(feed, drain).await;
+        let converted = conversion.await.map_err(io::Error::other)?;
+        written?;
+        converted
+    }
+}
+
+/// Run both futures to completion, returning the output of the second
+async fn join<A, B>(first: A, second: B) -> B::Output
+where
+    A: Future<Output = ()>,
+    B: Future,
+{
+    let mut first = pin!(first);
+    let mut second = pin!(second);
+    let mut first_done = false;
+    let mut second_output = None;
+    poll_fn(|cx| {
+        if !first_done {
+            first_done = first.as_mut().poll(cx).is_ready();
+        }
+        if second_output.is_none()
+            && let Poll::Ready(output) = second.as_mut().poll(cx)
+        {
+            second_output = Some(output);
+        }
+        match second_output.take() {
+            Some(output) if first_done => Poll::Ready(output),
+            output => {
+                second_output = output;
+                Poll::Pending
+            }
+        }
+    })
+    .await
+}
+
+/// Input of the converter thread, one line at a time
+struct LineReceiver {
+    lines: mpsc::Receiver<io::Result<String>>,
+    /// Current line with its line break
+    line: Vec<u8>,
+    /// Bytes of `line` already read
+    pos: usize,
+}
+
+impl Read for LineReceiver {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.line.len() {
+            let Some(line) = self.lines.blocking_recv() else {
+                return Ok(0);
+            };
+            self.line = line?.into_bytes();
+            self.line.push(b'\n');
+            self.pos = 0;
+        }
+        let n = buf.len().min(self.line.len() - self.pos);
+        buf[..n].copy_from_slice(&self.line[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Output of the converter thread, passed back to the runtime in chunks
+struct ChunkSender(mpsc::Sender<Vec<u8>>);
+
+impl Write for ChunkSender {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .blocking_send(buf.to_vec())
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "Output closed"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
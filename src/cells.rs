@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use std::io;
+
+/// Per-cell size limits applied before CSV escaping
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CellLimits {
+    /// Limit for columns without an override
+    pub(crate) max_chars: Option<usize>,
+    /// Column-specific limits, taking precedence over `max_chars`
+    pub(crate) column_max_chars: HashMap<String, usize>,
+    /// Replace `\x...` hex blobs with a length and hash summary
+    pub(crate) summarize_blobs: bool,
+}
+
+impl CellLimits {
+    fn is_active(&self) -> bool {
+        self.max_chars.is_some() || !self.column_max_chars.is_empty() || self.summarize_blobs
+    }
+
+    /// Shorten oversized cells in place
+    pub(crate) fn apply(&self, columns: &[String], rows: &mut [Vec<String>]) -> io::Result<()> {
+        if !self.is_active() {
+            return Ok(());
+        }
+
+        if let Some(unknown) = self.column_max_chars.keys().find(|c| !columns.contains(c)) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Unknown column for cell limit: {}", unknown),
+            ));
+        }
+
+        let limits: Vec<Option<usize>> = columns
+            .iter()
+            .map(|c| self.column_max_chars.get(c).copied().or(self.max_chars))
+            .collect();
+
+        for row in rows.iter_mut() {
+            for (cell, limit) in row.iter_mut().zip(&limits) {
+                if self.summarize_blobs
+                    && let Some(summary) = summarize_blob(cell)
+                {
+                    *cell = summary;
+                } else if let Some(limit) = limit
+                    && let Some(truncated) = truncate_cell(cell, *limit)
+                {
+                    *cell = truncated;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Cut a cell to `max_chars` characters, noting how many were dropped
+///
+/// Returns `None` if the cell already fits, or if the note would make it no
+/// shorter.
+fn truncate_cell(cell: &str, max_chars: usize) -> Option<String> {
+    let (cut, _) = cell.char_indices().nth(max_chars)?;
+    let omitted = cell[cut..].chars().count();
+    let truncated = format!("{}…[+{} chars]", &cell[..cut], omitted);
+    (truncated.chars().count() < max_chars + omitted).then_some(truncated)
+}
+
+/// Describe a PostgreSQL hex-format `bytea` value by length and FNV-1a hash
+///
+/// Returns `None` if the cell is not a `\x...` hex blob.
+fn summarize_blob(cell: &str) -> Option<String> {
+    let hex = cell.strip_prefix("\\x")?;
+    if hex.is_empty() || hex.len() % 2 != 0 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for pair in hex.as_bytes().chunks(2) {
+        let byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+
+    Some(format!(
+        "\\x[{} bytes fnv1a64={:016x}]",
+        hex.len() / 2,
+        hash
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_cell() {
+        assert_eq!(truncate_cell("short", 10), None);
+        assert_eq!(truncate_cell("exactly", 7), None);
+        assert_eq!(
+            truncate_cell("abcdefghijklmnopqrstuvwxyz", 4),
+            Some("abcd…[+22 chars]".to_string())
+        );
+        assert_eq!(
+            truncate_cell("héllo wörld, and more", 5),
+            Some("héllo…[+16 chars]".to_string())
+        );
+        // The note would add more than it removes
+        assert_eq!(truncate_cell("abcdefghijk", 10), None);
+        assert_eq!(truncate_cell("héllo wörld", 5), None);
+    }
+
+    #[test]
+    fn test_summarize_blob() {
+        assert_eq!(summarize_blob("plain text"), None);
+        assert_eq!(summarize_blob("\\xZZ"), None);
+        assert_eq!(summarize_blob("\\x"), None);
+        // FNV-1a of the single byte 0x61 ("a")
+        assert_eq!(
+            summarize_blob("\\x61"),
+            Some("\\x[1 bytes fnv1a64=af63dc4c8601ec8c]".to_string())
+        );
+    }
+
+    #[test]
+    fn test_column_override_wins() {
+        let limits = CellLimits {
+            max_chars: Some(3),
+            column_max_chars: HashMap::from([("body".to_string(), 6)]),
+            summarize_blobs: false,
+        };
+        let columns = vec!["title".to_string(), "body".to_string()];
+        let value = "abcdefghijklmnopqrstuvwxyz";
+        let mut rows = vec![vec![value.to_string(), value.to_string()]];
+        limits.apply(&columns, &mut rows).unwrap();
+        assert_eq!(rows[0], vec!["abc…[+23 chars]", "abcdef…[+20 chars]"]);
+    }
+
+    #[test]
+    fn test_unknown_override_column() {
+        let limits = CellLimits {
+            column_max_chars: HashMap::from([("missing".to_string(), 6)]),
+            ..CellLimits::default()
+        };
+        let err = limits.apply(&["id".to_string()], &mut []).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Unknown column for cell limit: missing")
+        );
+    }
+}
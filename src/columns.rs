@@ -0,0 +1,283 @@
+use std::io;
+use std::str::FromStr;
+
+use crate::check_column_name;
+use crate::pattern::{Regex, wildcard_match};
+
+/// Selects columns by exact name, glob (`created_*`) or regex (`/_id$/`)
+#[derive(Debug, Clone)]
+pub struct ColumnSelector {
+    kind: SelectorKind,
+    source: String,
+}
+
+#[derive(Debug, Clone)]
+enum SelectorKind {
+    Exact,
+    Glob,
+    Regex(Regex),
+}
+
+impl ColumnSelector {
+    /// Whether the selector matches the column `name`
+    pub fn matches(&self, name: &str) -> bool {
+        match &self.kind {
+            SelectorKind::Exact => self.source == name,
+            SelectorKind::Glob => wildcard_match(&self.source, name, '*', '?'),
+            SelectorKind::Regex(regex) => regex.is_match(name),
+        }
+    }
+
+    fn is_exact(&self) -> bool {
+        matches!(self.kind, SelectorKind::Exact)
+    }
+
+    /// Parse a comma-separated selector list such as `id,name,created_*`
+    pub fn parse_list(list: &str) -> io::Result<Vec<Self>> {
+        list.split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::parse)
+            .collect()
+    }
+}
+
+impl FromStr for ColumnSelector {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> io::Result<Self> {
+        if let Some(pattern) = s.strip_prefix('/').and_then(|rest| rest.strip_suffix('/')) {
+            return Ok(ColumnSelector {
+                kind: SelectorKind::Regex(Regex::new(pattern)?),
+                source: pattern.to_string(),
+            });
+        }
+
+        let kind = if s.contains(['*', '?']) {
+            SelectorKind::Glob
+        } else {
+            SelectorKind::Exact
+        };
+        Ok(ColumnSelector {
+            kind,
+            source: s.to_string(),
+        })
+    }
+}
+
+/// Column selection, ordering and renaming applied to a parsed table
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Projection {
+    /// Columns to keep, in output order (`None` keeps all)
+    pub(crate) include: Option<Vec<ColumnSelector>>,
+    pub(crate) exclude: Vec<ColumnSelector>,
+    /// `(old, new)` column name pairs
+    pub(crate) renames: Vec<(String, String)>,
+}
+
+impl Projection {
+    fn is_identity(&self) -> bool {
+        self.include.is_none() && self.exclude.is_empty() && self.renames.is_empty()
+    }
+
+    /// Select, reorder and rename columns in place
+    pub(crate) fn apply(
+        &self,
+        columns: &mut Vec<String>,
+        rows: &mut [Vec<String>],
+    ) -> io::Result<()> {
+        if self.is_identity() {
+            return Ok(());
+        }
+
+        let selected = self.select(columns)?;
+
+        for row in rows.iter_mut() {
+            let mut cells: Vec<Option<String>> =
+                std::mem::take(row).into_iter().map(Some).collect();
+            *row = selected
+                .iter()
+                .map(|(idx, _)| cells[*idx].take().unwrap_or_default())
+                .collect();
+        }
+        *columns = selected.into_iter().map(|(_, name)| name).collect();
+
+        Ok(())
+    }
+
+    /// Source index and output name of each emitted column, in output order
+    pub(crate) fn select(&self, columns: &[String]) -> io::Result<Vec<(usize, String)>> {
+        let order = self.column_order(columns)?;
+
+        for (old, new) in &self.renames {
+            if !order.iter().any(|&idx| &columns[idx] == old) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Unknown column to rename: {}", old),
+                ));
+            }
+            check_column_name(new)?;
+        }
+
+        let selected: Vec<(usize, String)> = order
+            .into_iter()
+            .map(|idx| (idx, self.output_name(&columns[idx])))
+            .collect();
+        for (_, new) in &self.renames {
+            if selected.iter().filter(|(_, name)| name == new).count() > 1 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Duplicate column name after renaming: {}", new),
+                ));
+            }
+        }
+        Ok(selected)
+    }
+
+    /// Name under which a source column is emitted
+    pub(crate) fn output_name(&self, name: &str) -> String {
+        self.renames
+            .iter()
+            .find(|(old, _)| old == name)
+            .map_or_else(|| name.to_string(), |(_, new)| new.clone())
+    }
+
+    /// Indices of the source columns to emit, in output order
+    fn column_order(&self, columns: &[String]) -> io::Result<Vec<usize>> {
+        let mut order: Vec<usize> = match &self.include {
+            None => (0..columns.len()).collect(),
+            Some(selectors) => {
+                let mut order = Vec::new();
+                for selector in selectors {
+                    let matched: Vec<usize> = (0..columns.len())
+                        .filter(|&idx| selector.matches(&columns[idx]))
+                        .collect();
+                    if matched.is_empty() && selector.is_exact() {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            format!("Unknown column: {}", selector.source),
+                        ));
+                    }
+                    for idx in matched {
+                        if !order.contains(&idx) {
+                            order.push(idx);
+                        }
+                    }
+                }
+                order
+            }
+        };
+
+        order.retain(|&idx| !self.exclude.iter().any(|s| s.matches(&columns[idx])));
+
+        if order.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Column selection leaves no columns",
+            ));
+        }
+
+        Ok(order)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> (Vec<String>, Vec<Vec<String>>) {
+        let columns = ["id", "name", "email", "created_at", "updated_at"]
+            .map(String::from)
+            .to_vec();
+        let rows = vec![
+            ["1", "Alice", "a@x", "2025-01-01", "2025-02-01"]
+                .map(String::from)
+                .to_vec(),
+        ];
+        (columns, rows)
+    }
+
+    #[test]
+    fn test_select_and_reorder() {
+        let (mut columns, mut rows) = table();
+        let projection = Projection {
+            include: Some(ColumnSelector::parse_list("name,id").unwrap()),
+            ..Projection::default()
+        };
+        projection.apply(&mut columns, &mut rows).unwrap();
+        assert_eq!(columns, vec!["name", "id"]);
+        assert_eq!(rows[0], vec!["Alice", "1"]);
+    }
+
+    #[test]
+    fn test_glob_regex_and_exclude() {
+        let (mut columns, mut rows) = table();
+        let projection = Projection {
+            include: Some(ColumnSelector::parse_list("id,*_at,/^(name|email)$/").unwrap()),
+            exclude: ColumnSelector::parse_list("updated_*").unwrap(),
+            renames: vec![("created_at".to_string(), "created".to_string())],
+        };
+        projection.apply(&mut columns, &mut rows).unwrap();
+        assert_eq!(columns, vec!["id", "created", "name", "email"]);
+        assert_eq!(rows[0], vec!["1", "2025-01-01", "Alice", "a@x"]);
+    }
+
+    #[test]
+    fn test_unknown_columns_rejected() {
+        let (mut columns, mut rows) = table();
+        let projection = Projection {
+            include: Some(ColumnSelector::parse_list("nope").unwrap()),
+            ..Projection::default()
+        };
+        assert!(projection.apply(&mut columns, &mut rows).is_err());
+
+        let projection = Projection {
+            renames: vec![("nope".to_string(), "x".to_string())],
+            ..Projection::default()
+        };
+        assert!(projection.apply(&mut columns, &mut rows).is_err());
+    }
+
+    #[test]
+    fn test_invalid_new_names_rejected() {
+        let (columns, _) = table();
+        for (new, message) in [
+            (
+                "full name",
+                "Invalid column name: \"full name\" (use letters, digits and _)",
+            ),
+            (
+                "x{y}",
+                "Invalid column name: \"x{y}\" (use letters, digits and _)",
+            ),
+            ("", "Invalid column name: \"\" (use letters, digits and _)"),
+            ("id", "Duplicate column name after renaming: id"),
+        ] {
+            let projection = Projection {
+                renames: vec![("name".to_string(), new.to_string())],
+                ..Projection::default()
+            };
+            let err = projection.select(&columns).unwrap_err();
+            assert_eq!(err.to_string(), message);
+        }
+
+        // The column the new name was taken from is not emitted
+        let projection = Projection {
+            exclude: ColumnSelector::parse_list("id").unwrap(),
+            renames: vec![("name".to_string(), "id".to_string())],
+            ..Projection::default()
+        };
+        assert!(projection.select(&columns).is_ok());
+    }
+
+    #[test]
+    fn test_excluding_everything_fails() {
+        let (mut columns, mut rows) = table();
+        let projection = Projection {
+            exclude: ColumnSelector::parse_list("*").unwrap(),
+            ..Projection::default()
+        };
+        let err = projection.apply(&mut columns, &mut rows).unwrap_err();
+        assert!(err.to_string().contains("leaves no columns"));
+    }
+}
@@ -0,0 +1,124 @@
+use std::fmt;
+
+/// A column with the same value (or NULL) in every row, moved into the header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Constant {
+    pub(crate) column: String,
+    /// The shared value; `None` if the column is NULL everywhere
+    pub(crate) value: Option<String>,
+}
+
+/// Renders as `column=value`, with `NULL` for a NULL column and quoting for
+/// values that would be ambiguous in the header (including the text `NULL`)
+impl fmt::Display for Constant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.value {
+            None => write!(f, "{}=NULL", self.column),
+            Some(value)
+                if value == "NULL" || value.contains([',', '"', ':', '=', '|', '\n', '\r']) =>
+            {
+                write!(f, "{}=\"{}\"", self.column, value.replace('"', "\"\""))
+            }
+            Some(value) => write!(f, "{}={}", self.column, value),
+        }
+    }
+}
+
+/// Remove columns that hold a single value in every row, returning them
+///
+/// Needs at least two rows (a single row is constant by definition) and always
+/// leaves at least one column in the data block.
+pub(crate) fn fold(columns: &mut Vec<String>, rows: &mut [Vec<String>]) -> Vec<Constant> {
+    if rows.len() < 2 {
+        return Vec::new();
+    }
+
+    let mut constant: Vec<bool> = (0..columns.len())
+        .map(|col| rows.iter().all(|row| row[col] == rows[0][col]))
+        .collect();
+    if constant.iter().all(|&c| c) {
+        constant[0] = false;
+    }
+    if !constant.contains(&true) {
+        return Vec::new();
+    }
+
+    let folded = columns
+        .iter()
+        .zip(&constant)
+        .enumerate()
+        .filter(|(_, (_, is_constant))| **is_constant)
+        .map(|(col, (name, _))| Constant {
+            column: name.clone(),
+            value: Some(rows[0][col].clone()).filter(|v| !v.is_empty()),
+        })
+        .collect();
+
+    let keep = |values: Vec<String>| -> Vec<String> {
+        values
+            .into_iter()
+            .zip(&constant)
+            .filter(|(_, is_constant)| !**is_constant)
+            .map(|(value, _)| value)
+            .collect()
+    };
+    *columns = keep(std::mem::take(columns));
+    for row in rows.iter_mut() {
+        *row = keep(std::mem::take(row));
+    }
+
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_fold_constant_and_null_columns() {
+        let mut columns = strings(&["id", "tenant_id", "name", "deleted_at"]);
+        let mut rows = vec![
+            strings(&["1", "42", "Alice", ""]),
+            strings(&["2", "42", "Bob", ""]),
+        ];
+
+        let folded = fold(&mut columns, &mut rows);
+
+        assert_eq!(columns, strings(&["id", "name"]));
+        assert_eq!(rows, vec![strings(&["1", "Alice"]), strings(&["2", "Bob"])]);
+        let rendered: Vec<String> = folded.iter().map(|c| c.to_string()).collect();
+        assert_eq!(rendered, vec!["tenant_id=42", "deleted_at=NULL"]);
+    }
+
+    #[test]
+    fn test_single_row_not_folded() {
+        let mut columns = strings(&["id", "name"]);
+        let mut rows = vec![strings(&["1", "Alice"])];
+        assert!(fold(&mut columns, &mut rows).is_empty());
+        assert_eq!(columns.len(), 2);
+    }
+
+    #[test]
+    fn test_keeps_one_column() {
+        let mut columns = strings(&["a", "b"]);
+        let mut rows = vec![strings(&["x", "y"]), strings(&["x", "y"])];
+        let folded = fold(&mut columns, &mut rows);
+        assert_eq!(columns, strings(&["a"]));
+        assert_eq!(folded.len(), 1);
+    }
+
+    #[test]
+    fn test_ambiguous_values_are_quoted() {
+        let constant = |value: &str| Constant {
+            column: "c".to_string(),
+            value: Some(value.to_string()),
+        };
+        assert_eq!(constant("NULL").to_string(), "c=\"NULL\"");
+        assert_eq!(constant("a,b").to_string(), "c=\"a,b\"");
+        assert_eq!(constant("say \"hi\"").to_string(), "c=\"say \"\"hi\"\"\"");
+    }
+}
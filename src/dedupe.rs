@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+/// Name of the multiplicity column added by deduplication
+pub(crate) const COUNT_COLUMN: &str = "_count";
+
+/// Order of the distinct rows emitted by `--dedupe`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DedupeOrder {
+    /// Order in which each distinct row first appears
+    #[default]
+    FirstSeen,
+    /// Most frequent rows first, ties in first-seen order
+    Frequency,
+}
+
+impl FromStr for DedupeOrder {
+    type Err = io::Error;
+
+    /// Parse `first-seen` or `frequency`
+    fn from_str(s: &str) -> io::Result<Self> {
+        match s {
+            "first-seen" => Ok(DedupeOrder::FirstSeen),
+            "frequency" => Ok(DedupeOrder::Frequency),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Unknown dedupe order: {} (expected first-seen or frequency)",
+                    s
+                ),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for DedupeOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DedupeOrder::FirstSeen => write!(f, "first-seen"),
+            DedupeOrder::Frequency => write!(f, "frequency"),
+        }
+    }
+}
+
+/// Collapse identical rows into one, appending a `_count` column
+pub(crate) fn dedupe(
+    columns: &mut Vec<String>,
+    rows: &mut Vec<Vec<String>>,
+    order: DedupeOrder,
+) -> io::Result<()> {
+    if columns.iter().any(|c| c == COUNT_COLUMN) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Cannot dedupe: column {} already exists", COUNT_COLUMN),
+        ));
+    }
+
+    let mut distinct: Vec<(Vec<String>, usize)> = Vec::new();
+    let mut index: HashMap<Vec<String>, usize> = HashMap::new();
+    for row in std::mem::take(rows) {
+        match index.get(&row) {
+            Some(&i) => distinct[i].1 += 1,
+            None => {
+                index.insert(row.clone(), distinct.len());
+                distinct.push((row, 1));
+            }
+        }
+    }
+
+    if order == DedupeOrder::Frequency {
+        // Stable sort keeps first-seen order among equally frequent rows
+        distinct.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+    }
+
+    columns.push(COUNT_COLUMN.to_string());
+    *rows = distinct
+        .into_iter()
+        .map(|(mut row, count)| {
+            row.push(count.to_string());
+            row
+        })
+        .collect();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> (Vec<String>, Vec<Vec<String>>) {
+        let columns = vec!["level".to_string(), "message".to_string()];
+        let rows = [
+            ("INFO", "started"),
+            ("WARN", "slow query"),
+            ("WARN", "slow query"),
+            ("INFO", "started"),
+            ("WARN", "slow query"),
+            ("ERROR", ""),
+        ]
+        .iter()
+        .map(|(level, message)| vec![level.to_string(), message.to_string()])
+        .collect();
+        (columns, rows)
+    }
+
+    #[test]
+    fn test_first_seen_order() {
+        let (mut columns, mut rows) = table();
+        dedupe(&mut columns, &mut rows, DedupeOrder::FirstSeen).unwrap();
+
+        assert_eq!(columns, vec!["level", "message", "_count"]);
+        assert_eq!(
+            rows,
+            vec![
+                vec!["INFO", "started", "2"],
+                vec!["WARN", "slow query", "3"],
+                vec!["ERROR", "", "1"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_frequency_order() {
+        let (mut columns, mut rows) = table();
+        dedupe(&mut columns, &mut rows, DedupeOrder::Frequency).unwrap();
+
+        let counts: Vec<&str> = rows.iter().map(|r| r[2].as_str()).collect();
+        assert_eq!(counts, vec!["3", "2", "1"]);
+    }
+
+    #[test]
+    fn test_existing_count_column_rejected() {
+        let mut columns = vec!["_count".to_string()];
+        let mut rows = Vec::new();
+        assert!(dedupe(&mut columns, &mut rows, DedupeOrder::FirstSeen).is_err());
+    }
+
+    #[test]
+    fn test_parse_order() {
+        assert_eq!(
+            "frequency".parse::<DedupeOrder>().unwrap(),
+            DedupeOrder::Frequency
+        );
+        assert!("random".parse::<DedupeOrder>().is_err());
+    }
+}
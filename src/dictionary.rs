@@ -0,0 +1,288 @@
+use std::collections::HashMap;
+
+/// Value table for one dictionary-encoded column
+///
+/// Written between the schema header and the data block as
+/// `column={0:pending,1:shipped}`; data cells hold the codes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Legend {
+    pub(crate) column: String,
+    /// Original values, indexed by code
+    pub(crate) values: Vec<String>,
+}
+
+impl Legend {
+    /// Format the legend line (with trailing newline)
+    pub(crate) fn line(&self) -> String {
+        let entries: Vec<String> = self
+            .values
+            .iter()
+            .enumerate()
+            .map(|(code, value)| format!("{}:{}", code, escape_legend_value(value)))
+            .collect();
+        format!("{}={{{}}}\n", self.column, entries.join(","))
+    }
+
+    /// Parse a legend line for `column`, reporting the byte offset of any error
+    pub(crate) fn parse(column: &str, line: &str) -> Result<Self, (usize, String)> {
+        let prefix = format!("{}={{", column);
+        let body = line
+            .strip_prefix(&prefix)
+            .ok_or_else(|| (0, format!("expected legend for column {}", column)))?;
+        let base = prefix.len();
+
+        let chars: Vec<(usize, char)> = body.char_indices().collect();
+        let at = |i: usize| chars.get(i).map(|&(pos, c)| (base + pos, c));
+        let mut values = Vec::new();
+        let mut i = 0;
+
+        if at(0).map(|(_, c)| c) == Some('}') {
+            i = 1;
+        } else {
+            loop {
+                // CODE
+                let start = i;
+                while at(i).is_some_and(|(_, c)| c.is_ascii_digit()) {
+                    i += 1;
+                }
+                let code: String = chars[start..i].iter().map(|&(_, c)| c).collect();
+                let pos = at(start).map_or(line.len(), |(pos, _)| pos);
+                if code.parse::<usize>().ok() != Some(values.len()) {
+                    return Err((pos, format!("expected code {}", values.len())));
+                }
+                if at(i).map(|(_, c)| c) != Some(':') {
+                    return Err((
+                        at(i).map_or(line.len(), |(p, _)| p),
+                        "expected ':'".to_string(),
+                    ));
+                }
+                i += 1;
+
+                // VALUE, optionally quoted with doubled inner quotes
+                let mut value = String::new();
+                if at(i).map(|(_, c)| c) == Some('"') {
+                    let open = at(i).map_or(line.len(), |(p, _)| p);
+                    i += 1;
+                    loop {
+                        match at(i).map(|(_, c)| c) {
+                            None => return Err((open, "unterminated quoted value".to_string())),
+                            Some('"') if at(i + 1).map(|(_, c)| c) == Some('"') => {
+                                value.push('"');
+                                i += 2;
+                            }
+                            Some('"') => {
+                                i += 1;
+                                break;
+                            }
+                            Some(c) => {
+                                value.push(c);
+                                i += 1;
+                            }
+                        }
+                    }
+                } else {
+                    while let Some((_, c)) = at(i).filter(|&(_, c)| c != ',' && c != '}') {
+                        value.push(c);
+                        i += 1;
+                    }
+                }
+                values.push(value);
+
+                match at(i).map(|(_, c)| c) {
+                    Some(',') => i += 1,
+                    Some('}') => {
+                        i += 1;
+                        break;
+                    }
+                    _ => {
+                        return Err((
+                            at(i).map_or(line.len(), |(p, _)| p),
+                            "expected ',' or '}'".to_string(),
+                        ));
+                    }
+                }
+            }
+        }
+
+        if let Some((pos, _)) = at(i) {
+            return Err((pos, "unexpected text after legend".to_string()));
+        }
+
+        Ok(Legend {
+            column: column.to_string(),
+            values,
+        })
+    }
+
+    /// Original value for a code, if the legend has one
+    pub(crate) fn decode(&self, code: &str) -> Option<&str> {
+        code.parse::<usize>()
+            .ok()
+            .and_then(|code| self.values.get(code))
+            .map(String::as_str)
+    }
+}
+
+/// Quote legend values that contain legend or CSV syntax
+fn escape_legend_value(value: &str) -> String {
+    if value.contains([',', '"', ':', '{', '}', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Replace the values of low-cardinality text columns with short integer codes
+///
+/// A column is encoded when it has at most `max_distinct` distinct non-NULL
+/// values, is not purely numeric (codes would be confused with the data), and
+/// encoding makes the document smaller once its legend line is counted.
+/// The most frequent value gets code 0. NULL cells are left empty.
+pub(crate) fn encode(
+    columns: &[String],
+    rows: &mut [Vec<String>],
+    max_distinct: usize,
+) -> Vec<Legend> {
+    let mut legends = Vec::new();
+
+    for (col, name) in columns.iter().enumerate() {
+        let Some(values) = candidate_values(rows, col, max_distinct) else {
+            continue;
+        };
+
+        let legend = Legend {
+            column: name.clone(),
+            values,
+        };
+        let codes: HashMap<&str, String> = legend
+            .values
+            .iter()
+            .enumerate()
+            .map(|(code, value)| (value.as_str(), code.to_string()))
+            .collect();
+
+        let before: usize = rows.iter().map(|row| row[col].chars().count()).sum();
+        let after: usize = rows
+            .iter()
+            .map(|row| codes.get(row[col].as_str()).map_or(0, |code| code.len()))
+            .sum::<usize>()
+            + legend.line().chars().count();
+        if after >= before {
+            continue;
+        }
+
+        for row in rows.iter_mut() {
+            if let Some(code) = codes.get(row[col].as_str()) {
+                row[col] = code.clone();
+            }
+        }
+        legends.push(legend);
+    }
+
+    legends
+}
+
+/// Distinct values of a column ordered by descending frequency, if it qualifies
+fn candidate_values(rows: &[Vec<String>], col: usize, max_distinct: usize) -> Option<Vec<String>> {
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    let mut index: HashMap<&str, usize> = HashMap::new();
+
+    for row in rows {
+        let value = row[col].as_str();
+        if value.is_empty() {
+            continue;
+        }
+        match index.get(value) {
+            Some(&i) => counts[i].1 += 1,
+            None => {
+                if counts.len() == max_distinct {
+                    return None;
+                }
+                index.insert(value, counts.len());
+                counts.push((value, 1));
+            }
+        }
+    }
+
+    if counts.is_empty() || counts.iter().all(|(value, _)| value.parse::<f64>().is_ok()) {
+        return None;
+    }
+
+    // Stable sort keeps first-seen order among equally frequent values
+    counts.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+    Some(
+        counts
+            .into_iter()
+            .map(|(value, _)| value.to_string())
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(values: &[&str]) -> Vec<Vec<String>> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| vec![i.to_string(), v.to_string()])
+            .collect()
+    }
+
+    #[test]
+    fn test_encodes_repetitive_column() {
+        let columns = vec!["id".to_string(), "status".to_string()];
+        let mut rows = rows(&["shipped", "pending", "shipped", "", "shipped", "pending"]);
+        let legends = encode(&columns, &mut rows, 16);
+
+        assert_eq!(legends.len(), 1);
+        assert_eq!(legends[0].line(), "status={0:shipped,1:pending}\n");
+        let codes: Vec<&str> = rows.iter().map(|r| r[1].as_str()).collect();
+        assert_eq!(codes, vec!["0", "1", "0", "", "0", "1"]);
+    }
+
+    #[test]
+    fn test_skips_numeric_and_high_cardinality_columns() {
+        let columns = vec!["id".to_string(), "name".to_string()];
+        let mut rows = rows(&["alice", "bob", "carol", "dave"]);
+        assert!(encode(&columns, &mut rows, 3).is_empty());
+        assert_eq!(rows[0], vec!["0", "alice"]);
+    }
+
+    #[test]
+    fn test_skips_when_legend_costs_more() {
+        let columns = vec!["id".to_string(), "flag".to_string()];
+        let mut rows = rows(&["yes", "no"]);
+        assert!(encode(&columns, &mut rows, 16).is_empty());
+    }
+
+    #[test]
+    fn test_legend_round_trip_with_quoting() {
+        let legend = Legend {
+            column: "note".to_string(),
+            values: vec![
+                "a, b".to_string(),
+                "say \"hi\"".to_string(),
+                "x:y}".to_string(),
+                "plain".to_string(),
+            ],
+        };
+        let line = legend.line();
+        assert_eq!(
+            line,
+            "note={0:\"a, b\",1:\"say \"\"hi\"\"\",2:\"x:y}\",3:plain}\n"
+        );
+        assert_eq!(Legend::parse("note", line.trim_end()).unwrap(), legend);
+        assert_eq!(legend.decode("1"), Some("say \"hi\""));
+        assert_eq!(legend.decode("9"), None);
+    }
+
+    #[test]
+    fn test_legend_parse_errors() {
+        assert_eq!(Legend::parse("s", "s={0:a,2:b}").unwrap_err().0, 7);
+        assert!(Legend::parse("s", "t={0:a}").is_err());
+        assert!(Legend::parse("s", "s={0:a").is_err());
+        assert!(Legend::parse("s", "s={0:\"a}").is_err());
+    }
+}
@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+
+use crate::reader::{Row, ToseReader};
+use crate::writer::field;
+
+/// Fields of the `schema` entity, one record per added, dropped or moved column
+const SCHEMA_FIELDS: [&str; 4] = ["change", "column", "before", "after"];
+
+/// Compare two single-entity TOSE documents row by row, matching rows on the
+/// `keys` columns
+///
+/// Writes a multi-entity document with four entities: `schema` (added,
+/// dropped and moved columns, with their 1-based positions), `added` (rows
+/// only in `after`), `removed` (rows only in `before`) and `changed`, one
+/// `{KEYS...,column,old,new}` record per differing value of a column both
+/// documents have. `NULL` is an empty field and an empty string `""`, as
+/// [`crate::ToseWriter`] writes them. Folded constant columns are compared like the others, and
+/// both documents must hold complete results.
+pub fn diff_documents<A: Read, B: Read, W: Write>(
+    before: A,
+    after: B,
+    keys: &[String],
+    mut output: W,
+) -> io::Result<()> {
+    let before = Snapshot::read(before, keys, "before")?;
+    let after = Snapshot::read(after, keys, "after")?;
+
+    let mut schema = Vec::new();
+    for (idx, column) in before.columns.iter().enumerate() {
+        if !after.columns.contains(column) {
+            schema.push(vec![
+                Some("dropped".to_string()),
+                Some(column.clone()),
+                Some((idx + 1).to_string()),
+                None,
+            ]);
+        }
+    }
+    for (idx, column) in after.columns.iter().enumerate() {
+        if !before.columns.contains(column) {
+            schema.push(vec![
+                Some("added".to_string()),
+                Some(column.clone()),
+                None,
+                Some((idx + 1).to_string()),
+            ]);
+        }
+    }
+    // Columns whose place among the shared columns differs
+    let shared_before: Vec<&String> = before
+        .columns
+        .iter()
+        .filter(|c| after.columns.contains(c))
+        .collect();
+    let shared_after: Vec<&String> = after
+        .columns
+        .iter()
+        .filter(|c| before.columns.contains(c))
+        .collect();
+    for (column, moved) in shared_before.iter().zip(&shared_after) {
+        if column != moved {
+            schema.push(vec![
+                Some("moved".to_string()),
+                Some(column.to_string()),
+                Some((before.position(column) + 1).to_string()),
+                Some((after.position(column) + 1).to_string()),
+            ]);
+        }
+    }
+
+    let added: Vec<Row> = after
+        .rows
+        .iter()
+        .filter(|row| !before.index.contains_key(&after.key(row)))
+        .cloned()
+        .collect();
+    let removed: Vec<Row> = before
+        .rows
+        .iter()
+        .filter(|row| !after.index.contains_key(&before.key(row)))
+        .cloned()
+        .collect();
+
+    let mut changed = Vec::new();
+    for row in &before.rows {
+        let key = before.key(row);
+        let Some(&other) = after.index.get(&key) else {
+            continue;
+        };
+        let other = &after.rows[other];
+        for column in &after.columns {
+            if !before.columns.contains(column) || keys.contains(column) {
+                continue;
+            }
+            let old = &row[before.position(column)];
+            let new = &other[after.position(column)];
+            if old != new {
+                let mut record = key.clone();
+                record.push(Some(column.clone()));
+                record.push(old.clone());
+                record.push(new.clone());
+                changed.push(record);
+            }
+        }
+    }
+
+    let mut change_fields = keys.to_vec();
+    change_fields.extend(["column", "old", "new"].map(String::from));
+    let schema_fields = SCHEMA_FIELDS.map(String::from);
+    let entities = [
+        ("schema", &schema_fields[..], schema),
+        ("added", &after.columns[..], added),
+        ("removed", &before.columns[..], removed),
+        ("changed", &change_fields[..], changed),
+    ];
+
+    let names: Vec<&str> = entities.iter().map(|(name, _, _)| *name).collect();
+    writeln!(output, "@document[{}]{{{}}}:", names.len(), names.join(","))?;
+    for (name, fields, rows) in &entities {
+        writeln!(output)?;
+        writeln!(output, "{}[{}]{{{}}}:", name, rows.len(), fields.join(","))?;
+        for row in rows {
+            let cells: Vec<String> = row.iter().map(|cell| field(cell.as_deref())).collect();
+            writeln!(output, "{}", cells.join(","))?;
+        }
+    }
+    output.flush()
+}
+
+/// The rows of one document, indexed by key
+struct Snapshot {
+    columns: Vec<String>,
+    rows: Vec<Row>,
+    /// Positions of the key columns
+    key_columns: Vec<usize>,
+    index: HashMap<Row, usize>,
+}
+
+impl Snapshot {
+    fn read<R: Read>(input: R, keys: &[String], label: &str) -> io::Result<Self> {
+        let mut reader = ToseReader::new(input)?;
+        let header = reader.header().clone();
+        if header.is_partial() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "The {} document holds {} of {} rows; diff needs complete results",
+                    label, header.shown, header.total
+                ),
+            ));
+        }
+        // Folded constant columns take part in the comparison like any other
+        let mut columns = reader.columns().to_vec();
+        columns.extend(header.constants.iter().map(|(column, _)| column.clone()));
+        let rows = reader
+            .by_ref()
+            .map(|row| {
+                row.map(|mut row| {
+                    row.extend(header.constants.iter().map(|(_, value)| value.clone()));
+                    row
+                })
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let key_columns = keys
+            .iter()
+            .map(|key| {
+                columns.iter().position(|c| c == key).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!(
+                            "Unknown column for --key in the {} document: {}",
+                            label, key
+                        ),
+                    )
+                })
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let mut snapshot = Snapshot {
+            columns,
+            rows,
+            key_columns,
+            index: HashMap::new(),
+        };
+        for (idx, row) in snapshot.rows.iter().enumerate() {
+            let key = snapshot.key(row);
+            if snapshot.index.insert(key.clone(), idx).is_some() {
+                let values: Vec<String> = keys
+                    .iter()
+                    .zip(&key)
+                    .map(|(name, value)| format!("{}={}", name, value.as_deref().unwrap_or("NULL")))
+                    .collect();
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "Duplicate key {} in the {} document",
+                        values.join(","),
+                        label
+                    ),
+                ));
+            }
+        }
+        Ok(snapshot)
+    }
+
+    fn key(&self, row: &Row) -> Row {
+        self.key_columns
+            .iter()
+            .map(|&col| row[col].clone())
+            .collect()
+    }
+
+    fn position(&self, column: &str) -> usize {
+        self.columns
+            .iter()
+            .position(|c| c == column)
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diff(before: &str, after: &str, keys: &[&str]) -> io::Result<String> {
+        let keys: Vec<String> = keys.iter().map(|k| k.to_string()).collect();
+        let mut output = Vec::new();
+        diff_documents(before.as_bytes(), after.as_bytes(), &keys, &mut output)?;
+        Ok(String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn test_rows_added_removed_and_changed() {
+        let before = "users[3]{id,name,plan}:\n1,Alice,free\n2,Bob,pro\n3,Carol,\n";
+        let after = "users[3]{id,name,plan}:\n1,Alice,pro\n3,Carol,free\n4,Dave,free\n";
+        assert_eq!(
+            diff(before, after, &["id"]).unwrap(),
+            "@document[4]{schema,added,removed,changed}:\n\n\
+             schema[0]{change,column,before,after}:\n\n\
+             added[1]{id,name,plan}:\n4,Dave,free\n\n\
+             removed[1]{id,name,plan}:\n2,Bob,pro\n\n\
+             changed[2]{id,column,old,new}:\n1,plan,free,pro\n3,plan,,free\n"
+        );
+    }
+
+    #[test]
+    fn test_schema_changes() {
+        let before = "t[1]{id,name,phone}:\n1,Alice,555\n";
+        let after = "t[1]{name,id,email}:\nAlice,1,a@example.com\n";
+        let output = diff(before, after, &["id"]).unwrap();
+        assert!(output.contains(
+            "schema[4]{change,column,before,after}:\ndropped,phone,3,\nadded,email,,3\nmoved,id,1,2\nmoved,name,2,1\n"
+        ));
+        assert!(output.contains("changed[0]{id,column,old,new}:\n"));
+    }
+
+    #[test]
+    fn test_null_and_empty_string_differ() {
+        let before = "t[2]{id,note}:\n1,\n2,\"\"\n";
+        let after = "t[2]{id,note}:\n1,\"\"\n2,\n";
+        let output = diff(before, after, &["id"]).unwrap();
+        assert!(output.ends_with("changed[2]{id,column,old,new}:\n1,note,,\"\"\n2,note,\"\",\n"));
+    }
+
+    #[test]
+    fn test_key_errors() {
+        let document = "t[2]{id}:\n1\n1\n";
+        let err = diff(document, document, &["id"]).unwrap_err();
+        assert_eq!(err.to_string(), "Duplicate key id=1 in the before document");
+        let err = diff(document, document, &["name"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unknown column for --key in the before document: name"
+        );
+    }
+}
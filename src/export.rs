@@ -0,0 +1,411 @@
+use std::io::{self, Read, Write};
+
+use crate::dedupe::COUNT_COLUMN;
+use crate::reader::{Row, ToseReader};
+
+/// Rows per `INSERT` statement unless set with [`SqlExporter::with_batch_size`]
+const DEFAULT_BATCH_SIZE: usize = 100;
+
+/// PostgreSQL keywords that cannot be used as unquoted table or column names,
+/// sorted for binary search
+const RESERVED_WORDS: &[&str] = &[
+    "all",
+    "analyse",
+    "analyze",
+    "and",
+    "any",
+    "array",
+    "as",
+    "asc",
+    "asymmetric",
+    "authorization",
+    "binary",
+    "both",
+    "case",
+    "cast",
+    "check",
+    "collate",
+    "collation",
+    "column",
+    "concurrently",
+    "constraint",
+    "create",
+    "cross",
+    "current_catalog",
+    "current_date",
+    "current_role",
+    "current_schema",
+    "current_time",
+    "current_timestamp",
+    "current_user",
+    "default",
+    "deferrable",
+    "desc",
+    "distinct",
+    "do",
+    "else",
+    "end",
+    "except",
+    "false",
+    "fetch",
+    "for",
+    "foreign",
+    "freeze",
+    "from",
+    "full",
+    "grant",
+    "group",
+    "having",
+    "ilike",
+    "in",
+    "initially",
+    "inner",
+    "intersect",
+    "into",
+    "is",
+    "isnull",
+    "join",
+    "lateral",
+    "leading",
+    "left",
+    "like",
+    "limit",
+    "localtime",
+    "localtimestamp",
+    "natural",
+    "not",
+    "notnull",
+    "null",
+    "offset",
+    "on",
+    "only",
+    "or",
+    "order",
+    "outer",
+    "overlaps",
+    "placing",
+    "primary",
+    "references",
+    "returning",
+    "right",
+    "select",
+    "session_user",
+    "similar",
+    "some",
+    "symmetric",
+    "system_user",
+    "table",
+    "tablesample",
+    "then",
+    "to",
+    "trailing",
+    "true",
+    "union",
+    "unique",
+    "user",
+    "using",
+    "variadic",
+    "verbose",
+    "when",
+    "where",
+    "window",
+    "with",
+];
+
+/// Turns TOSE documents back into SQL that loads them into PostgreSQL
+///
+/// Every entity of the document becomes a table of the same name. NULL fields
+/// become `NULL`, folded constant columns are restored, and deduplicated rows
+/// are repeated `_count` times. The child records of a nested result become a
+/// table named after the child entity, with the parent fields repeated before
+/// the child fields.
+#[derive(Debug, Clone)]
+pub struct SqlExporter {
+    batch_size: usize,
+    copy: bool,
+    quote_identifiers: bool,
+}
+
+impl SqlExporter {
+    pub fn new() -> Self {
+        SqlExporter {
+            batch_size: DEFAULT_BATCH_SIZE,
+            copy: false,
+            quote_identifiers: false,
+        }
+    }
+
+    /// Rows per `INSERT ... VALUES` statement
+    pub fn with_batch_size(mut self, rows: usize) -> Self {
+        self.batch_size = rows.max(1);
+        self
+    }
+
+    /// Write a `COPY ... FROM STDIN` payload instead of `INSERT` statements
+    pub fn with_copy(mut self, copy: bool) -> Self {
+        self.copy = copy;
+        self
+    }
+
+    /// Double-quote every table and column name, not only those that need it
+    pub fn with_quoted_identifiers(mut self, quote: bool) -> Self {
+        self.quote_identifiers = quote;
+        self
+    }
+
+    /// Export every entity of a TOSE document
+    pub fn export<R: Read, W: Write>(&self, input: R, mut output: W) -> io::Result<()> {
+        let mut entity = Some(ToseReader::new(input)?);
+        while let Some(mut reader) = entity {
+            self.export_entity(&mut reader, &mut output)?;
+            entity = reader.next_entity()?;
+        }
+        output.flush()
+    }
+
+    fn export_entity<R: Read, W: Write>(
+        &self,
+        reader: &mut ToseReader<R>,
+        output: &mut W,
+    ) -> io::Result<()> {
+        let header = reader.header().clone();
+        let mut columns = reader.columns().to_vec();
+        let counted =
+            header.deduped_from.is_some() && columns.last().is_some_and(|c| c == COUNT_COLUMN);
+        if counted {
+            columns.pop();
+        }
+        let constants: Vec<Option<String>> = header
+            .constants
+            .iter()
+            .map(|(_, value)| value.clone())
+            .collect();
+        columns.extend(header.constants.iter().map(|(column, _)| column.clone()));
+
+        if header.is_partial() {
+            writeln!(
+                output,
+                "-- {}: {} of {} rows",
+                header.entity, header.shown, header.total
+            )?;
+        }
+
+        let mut children = Vec::new();
+        let rows = std::iter::from_fn(|| {
+            let row = reader.next()?;
+            Some(row.and_then(|mut row| {
+                let times = if counted {
+                    Self::count(row.pop().flatten())?
+                } else {
+                    1
+                };
+                row.extend(constants.iter().cloned());
+                for _ in 0..times {
+                    for child in reader.children() {
+                        children.push(row.iter().chain(child).cloned().collect());
+                    }
+                }
+                Ok((row, times))
+            }))
+        });
+        self.write_table(&header.entity, &columns, rows, output)?;
+
+        // Child records become a table of their own, each preceded by the
+        // fields of its parent
+        if let Some(entity) = &header.nested
+            && !children.is_empty()
+        {
+            columns.extend(reader.child_columns().iter().cloned());
+            let rows = children.into_iter().map(|row| Ok((row, 1)));
+            self.write_table(entity, &columns, rows, output)?;
+        }
+        Ok(())
+    }
+
+    /// Write the statements loading rows into a table, each row repeated the
+    /// given number of times
+    fn write_table<W: Write>(
+        &self,
+        entity: &str,
+        columns: &[String],
+        rows: impl Iterator<Item = io::Result<(Row, usize)>>,
+        output: &mut W,
+    ) -> io::Result<()> {
+        let table = format!(
+            "{} ({})",
+            self.identifier(entity),
+            columns
+                .iter()
+                .map(|c| self.identifier(c))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        if self.copy {
+            writeln!(output, "COPY {} FROM STDIN;", table)?;
+        }
+        let mut batch = Vec::with_capacity(self.batch_size);
+        for row in rows {
+            let (row, times) = row?;
+            for _ in 0..times {
+                if self.copy {
+                    writeln!(output, "{}", Self::copy_row(&row))?;
+                    continue;
+                }
+                batch.push(Self::values(&row));
+                if batch.len() == self.batch_size {
+                    Self::insert(&table, &mut batch, output)?;
+                }
+            }
+        }
+        if self.copy {
+            writeln!(output, "\\.")?;
+        } else if !batch.is_empty() {
+            Self::insert(&table, &mut batch, output)?;
+        }
+        Ok(())
+    }
+
+    /// Write one `INSERT` statement for the batched rows
+    fn insert<W: Write>(table: &str, batch: &mut Vec<String>, output: &mut W) -> io::Result<()> {
+        writeln!(
+            output,
+            "INSERT INTO {} VALUES\n{};",
+            table,
+            batch.join(",\n")
+        )?;
+        batch.clear();
+        Ok(())
+    }
+
+    /// Repetitions of a deduplicated row
+    fn count(value: Option<String>) -> io::Result<usize> {
+        value
+            .as_deref()
+            .and_then(|v| v.parse().ok())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "Invalid {} value: {:?}",
+                        COUNT_COLUMN,
+                        value.unwrap_or_default()
+                    ),
+                )
+            })
+    }
+
+    /// A table or column name, double-quoted if requested or if PostgreSQL
+    /// would not read it back unquoted
+    fn identifier(&self, name: &str) -> String {
+        if self.quote_identifiers || Self::needs_quotes(name) {
+            format!("\"{}\"", name.replace('"', "\"\""))
+        } else {
+            name.to_string()
+        }
+    }
+
+    /// Whether a name is a reserved word or not of the form `[a-z_][a-z0-9_]*`
+    fn needs_quotes(name: &str) -> bool {
+        let mut chars = name.chars();
+        let simple = chars
+            .next()
+            .is_some_and(|c| c.is_ascii_lowercase() || c == '_')
+            && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+        !simple || RESERVED_WORDS.binary_search(&name).is_ok()
+    }
+
+    /// `('1', 'Alice', NULL)`
+    fn values(row: &Row) -> String {
+        let values: Vec<String> = row
+            .iter()
+            .map(|cell| match cell {
+                Some(value) => format!("'{}'", value.replace('\'', "''")),
+                None => "NULL".to_string(),
+            })
+            .collect();
+        format!("({})", values.join(", "))
+    }
+
+    /// One line of COPY text format: tab-separated, `\N` for NULL
+    fn copy_row(row: &Row) -> String {
+        let fields: Vec<String> = row
+            .iter()
+            .map(|cell| match cell {
+                Some(value) => value
+                    .replace('\\', "\\\\")
+                    .replace('\t', "\\t")
+                    .replace('\n', "\\n")
+                    .replace('\r', "\\r"),
+                None => "\\N".to_string(),
+            })
+            .collect();
+        fields.join("\t")
+    }
+}
+
+impl Default for SqlExporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn export(exporter: SqlExporter, input: &str) -> String {
+        let mut output = Vec::new();
+        exporter.export(input.as_bytes(), &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_insert_batches() {
+        let input = "users[3]{id,name}:\n1,O'Brien\n2,\n3,\"\"\n";
+        assert_eq!(
+            export(SqlExporter::new().with_batch_size(2), input),
+            "INSERT INTO users (id, name) VALUES\n('1', 'O''Brien'),\n('2', NULL);\nINSERT INTO users (id, name) VALUES\n('3', '');\n"
+        );
+    }
+
+    #[test]
+    fn test_copy_escapes_text_format() {
+        let input = "notes[2]{id,body}:\n1,\"a\tb\\c\nd\"\n2,\n";
+        assert_eq!(
+            export(SqlExporter::new().with_copy(true), input),
+            "COPY notes (id, body) FROM STDIN;\n1\ta\\tb\\\\c\\nd\n2\t\\N\n\\.\n"
+        );
+    }
+
+    #[test]
+    fn test_restores_constants_and_duplicates() {
+        let input =
+            "logs[2, deduped from 3]{level,_count}|tenant=7,deleted_at=NULL:\nINFO,2\nWARN,1\n";
+        assert_eq!(
+            export(SqlExporter::new().with_quoted_identifiers(true), input),
+            "INSERT INTO \"logs\" (\"level\", \"tenant\", \"deleted_at\") VALUES\n('INFO', '7', NULL),\n('INFO', '7', NULL),\n('WARN', '7', NULL);\n"
+        );
+    }
+
+    #[test]
+    fn test_exports_child_records() {
+        let input = "p[2, nested=items]{o}:\n1\n  items[2]{sku,qty}:\n  A1,2\n  B7,\n2\n  items[0]{sku,qty}:\n";
+        assert_eq!(
+            export(SqlExporter::new(), input),
+            "INSERT INTO p (o) VALUES\n('1'),\n('2');\nINSERT INTO items (o, sku, qty) VALUES\n('1', 'A1', '2'),\n('1', 'B7', NULL);\n"
+        );
+    }
+
+    #[test]
+    fn test_quotes_reserved_and_unusual_names() {
+        let mut sorted = RESERVED_WORDS.to_vec();
+        sorted.sort_unstable();
+        assert_eq!(sorted, RESERVED_WORDS);
+
+        let input = "result_stats[1]{column,type,Total,_n2}:\nid,integer,1,2\n";
+        assert_eq!(
+            export(SqlExporter::new(), input),
+            "INSERT INTO result_stats (\"column\", type, \"Total\", _n2) VALUES\n('id', 'integer', '1', '2');\n"
+        );
+    }
+}
@@ -0,0 +1,605 @@
+//! Row filter expressions for `--where`
+//!
+//! ```text
+//! expr       := and (("||" | OR) and)*
+//! and        := not (("&&" | AND) not)*
+//! not        := ("!" | NOT) not | comparison
+//! comparison := operand [ op operand | IS [NOT] NULL | [NOT] LIKE operand ]
+//! op         := "==" | "=" | "!=" | "<>" | "<" | "<=" | ">" | ">="
+//! operand    := column | `quoted column` | "string" | 'string' | number | NULL | "(" expr ")"
+//! ```
+//!
+//! Comparisons are numeric when both sides are finite decimal numbers and
+//! textual otherwise. Any comparison involving NULL (an empty cell) is false,
+//! as is any but `!=` involving `NaN`.
+
+use std::cmp::Ordering;
+use std::io;
+use std::str::FromStr;
+
+use crate::pattern::wildcard_match;
+
+/// A parsed `--where` expression, evaluated against each row
+#[derive(Debug, Clone)]
+pub struct RowFilter {
+    expr: Expr,
+    source: String,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Operand, CompareOp, Operand),
+    IsNull(Operand, bool),
+    Like(Operand, Operand, bool),
+    /// A bare operand used as a condition: true unless NULL, `f`, `false` or `0`
+    Truthy(Operand),
+}
+
+#[derive(Debug, Clone)]
+enum Operand {
+    Column(String),
+    Literal(String),
+    Null,
+    Group(Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl RowFilter {
+    /// Check that every referenced column exists
+    pub(crate) fn check_columns(&self, columns: &[String]) -> io::Result<()> {
+        let mut referenced = Vec::new();
+        self.expr.columns(&mut referenced);
+        match referenced.into_iter().find(|c| !columns.contains(c)) {
+            Some(unknown) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Unknown column in --where expression: {}", unknown),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether `row` satisfies the expression
+    pub(crate) fn matches(&self, columns: &[String], row: &[String]) -> bool {
+        self.expr.eval(&|name: &str| {
+            columns
+                .iter()
+                .position(|c| c == name)
+                .map(|idx| row[idx].as_str())
+        })
+    }
+}
+
+impl FromStr for RowFilter {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> io::Result<Self> {
+        let tokens = tokenize(s)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.or()?;
+        if let Some((pos, token)) = parser.tokens.get(parser.pos) {
+            return Err(syntax_error(
+                *pos,
+                &format!("unexpected {}", token.describe()),
+            ));
+        }
+        Ok(RowFilter {
+            expr,
+            source: s.to_string(),
+        })
+    }
+}
+
+impl std::fmt::Display for RowFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+type Lookup<'a> = dyn Fn(&str) -> Option<&'a str> + 'a;
+
+impl Expr {
+    fn eval<'a>(&self, lookup: &Lookup<'a>) -> bool {
+        match self {
+            Expr::Or(a, b) => a.eval(lookup) || b.eval(lookup),
+            Expr::And(a, b) => a.eval(lookup) && b.eval(lookup),
+            Expr::Not(inner) => !inner.eval(lookup),
+            Expr::Compare(a, op, b) => match (a.value(lookup), b.value(lookup)) {
+                (Some(a), Some(b)) => {
+                    let ordering = compare(&a, &b);
+                    match op {
+                        CompareOp::Eq => ordering == Some(Ordering::Equal),
+                        CompareOp::Ne => ordering != Some(Ordering::Equal),
+                        CompareOp::Lt => ordering == Some(Ordering::Less),
+                        CompareOp::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+                        CompareOp::Gt => ordering == Some(Ordering::Greater),
+                        CompareOp::Ge => {
+                            matches!(ordering, Some(Ordering::Greater | Ordering::Equal))
+                        }
+                    }
+                }
+                _ => false,
+            },
+            Expr::IsNull(operand, negated) => operand.value(lookup).is_none() != *negated,
+            Expr::Like(operand, pattern, negated) => {
+                match (operand.value(lookup), pattern.value(lookup)) {
+                    (Some(value), Some(pattern)) => {
+                        wildcard_match(&pattern, &value, '%', '_') != *negated
+                    }
+                    _ => false,
+                }
+            }
+            Expr::Truthy(operand) => operand
+                .value(lookup)
+                .is_some_and(|v| !matches!(v.as_str(), "f" | "false" | "0")),
+        }
+    }
+
+    fn columns(&self, out: &mut Vec<String>) {
+        match self {
+            Expr::Or(a, b) | Expr::And(a, b) => {
+                a.columns(out);
+                b.columns(out);
+            }
+            Expr::Not(inner) => inner.columns(out),
+            Expr::Compare(a, _, b) | Expr::Like(a, b, _) => {
+                a.columns(out);
+                b.columns(out);
+            }
+            Expr::IsNull(a, _) | Expr::Truthy(a) => a.columns(out),
+        }
+    }
+}
+
+impl Operand {
+    /// The operand's value, or `None` for NULL (an empty cell)
+    fn value<'a>(&self, lookup: &Lookup<'a>) -> Option<String> {
+        match self {
+            Operand::Column(name) => lookup(name).filter(|v| !v.is_empty()).map(str::to_string),
+            Operand::Literal(value) => Some(value.clone()),
+            Operand::Null => None,
+            Operand::Group(expr) => Some(expr.eval(lookup).to_string()),
+        }
+    }
+
+    fn columns(&self, out: &mut Vec<String>) {
+        match self {
+            Operand::Column(name) => out.push(name.clone()),
+            Operand::Group(expr) => expr.columns(out),
+            Operand::Literal(_) | Operand::Null => {}
+        }
+    }
+}
+
+/// Compare numerically when both sides are numbers, otherwise as text;
+/// `None` when either side is `NaN`, which is unordered
+fn compare(a: &str, b: &str) -> Option<Ordering> {
+    if is_nan(a) || is_nan(b) {
+        return None;
+    }
+    match (decimal(a), decimal(b)) {
+        (Some(x), Some(y)) => x.partial_cmp(&y),
+        _ => Some(a.cmp(b)),
+    }
+}
+
+/// Whether a value is PostgreSQL's `NaN`
+fn is_nan(value: &str) -> bool {
+    value.trim().eq_ignore_ascii_case("nan")
+}
+
+/// The value of a finite decimal literal such as `-1`, `2.50` or `1e+20`;
+/// `NaN`, `Infinity` and other text are not numbers
+pub(crate) fn decimal(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let unsigned = text.strip_prefix(['-', '+']).unwrap_or(text);
+    let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+        Some(pos) => (&unsigned[..pos], Some(&unsigned[pos + 1..])),
+        None => (unsigned, None),
+    };
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+    let valid = !(whole.is_empty() && fraction.is_empty())
+        && digits(whole)
+        && digits(fraction)
+        && exponent.is_none_or(|exponent| {
+            let exponent = exponent.strip_prefix(['-', '+']).unwrap_or(exponent);
+            !exponent.is_empty() && digits(exponent)
+        });
+    if !valid {
+        return None;
+    }
+    text.parse::<f64>().ok().filter(|value| value.is_finite())
+}
+
+fn syntax_error(pos: usize, message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!(
+            "Invalid --where expression at position {}: {}",
+            pos, message
+        ),
+    )
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    QuotedIdent(String),
+    Str(String),
+    Number(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Ident(s) | Token::Number(s) => format!("'{}'", s),
+            Token::QuotedIdent(s) => format!("`{}`", s),
+            Token::Str(s) => format!("string \"{}\"", s),
+            Token::Op(op) => format!("'{}'", op),
+            Token::LParen => "'('".to_string(),
+            Token::RParen => "')'".to_string(),
+        }
+    }
+
+    /// Case-insensitive keyword check for bare identifiers
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self, Token::Ident(s) if s.eq_ignore_ascii_case(keyword))
+    }
+}
+
+fn tokenize(input: &str) -> io::Result<Vec<(usize, Token)>> {
+    // Longest operators first so `<=` is not read as `<`
+    const OPERATORS: [&str; 11] = ["==", "!=", "<>", "<=", ">=", "&&", "||", "=", "<", ">", "!"];
+
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let token = match c {
+            '(' => {
+                i += 1;
+                Token::LParen
+            }
+            ')' => {
+                i += 1;
+                Token::RParen
+            }
+            '"' | '\'' | '`' => {
+                // Quotes are escaped by doubling them, as in SQL
+                let mut value = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return Err(syntax_error(start, "unterminated quote")),
+                        Some(&q) if q == c && chars.get(i + 1) == Some(&c) => {
+                            value.push(c);
+                            i += 2;
+                        }
+                        Some(&q) if q == c => {
+                            i += 1;
+                            break;
+                        }
+                        Some(&other) => {
+                            value.push(other);
+                            i += 1;
+                        }
+                    }
+                }
+                if c == '`' {
+                    Token::QuotedIdent(value)
+                } else {
+                    Token::Str(value)
+                }
+            }
+            c if c.is_ascii_digit()
+                || (c == '-'
+                    && chars
+                        .get(i + 1)
+                        .is_some_and(|n| n.is_ascii_digit() || *n == '.'))
+                || (c == '.' && chars.get(i + 1).is_some_and(|n| n.is_ascii_digit())) =>
+            {
+                i += 1;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                Token::Number(chars[start..i].iter().collect())
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                while i < chars.len()
+                    && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '.')
+                {
+                    i += 1;
+                }
+                Token::Ident(chars[start..i].iter().collect())
+            }
+            _ => {
+                let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+                let op = OPERATORS
+                    .iter()
+                    .find(|op| rest.starts_with(*op))
+                    .ok_or_else(|| syntax_error(start, &format!("unexpected character '{}'", c)))?;
+                i += op.chars().count();
+                Token::Op(op)
+            }
+        };
+        tokens.push((start, token));
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, t)| t)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .or(self.tokens.last())
+            .map_or(0, |(pos, _)| *pos)
+    }
+
+    fn eat_op(&mut self, ops: &[&str]) -> Option<&'static str> {
+        match self.peek() {
+            Some(Token::Op(op)) if ops.contains(op) => {
+                let op = *op;
+                self.pos += 1;
+                Some(op)
+            }
+            _ => None,
+        }
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if self.peek().is_some_and(|t| t.is_keyword(keyword)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn or(&mut self) -> io::Result<Expr> {
+        let mut expr = self.and()?;
+        while self.eat_op(&["||"]).is_some() || self.eat_keyword("or") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> io::Result<Expr> {
+        let mut expr = self.not()?;
+        while self.eat_op(&["&&"]).is_some() || self.eat_keyword("and") {
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> io::Result<Expr> {
+        if self.eat_op(&["!"]).is_some() || self.eat_keyword("not") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> io::Result<Expr> {
+        let left = self.operand()?;
+
+        if let Some(op) = self.eat_op(&["==", "=", "!=", "<>", "<", "<=", ">", ">="]) {
+            let op = match op {
+                "==" | "=" => CompareOp::Eq,
+                "!=" | "<>" => CompareOp::Ne,
+                "<" => CompareOp::Lt,
+                "<=" => CompareOp::Le,
+                ">" => CompareOp::Gt,
+                _ => CompareOp::Ge,
+            };
+            return Ok(Expr::Compare(left, op, self.operand()?));
+        }
+
+        if self.eat_keyword("is") {
+            let negated = self.eat_keyword("not");
+            if !self.eat_keyword("null") {
+                return Err(syntax_error(self.position(), "expected NULL after IS"));
+            }
+            return Ok(Expr::IsNull(left, negated));
+        }
+
+        let negated = self.peek().is_some_and(|t| t.is_keyword("not"))
+            && self
+                .tokens
+                .get(self.pos + 1)
+                .is_some_and(|(_, t)| t.is_keyword("like"));
+        if negated {
+            self.pos += 1;
+        }
+        if self.eat_keyword("like") {
+            return Ok(Expr::Like(left, self.operand()?, negated));
+        }
+
+        match left {
+            Operand::Group(expr) => Ok(*expr),
+            other => Ok(Expr::Truthy(other)),
+        }
+    }
+
+    fn operand(&mut self) -> io::Result<Operand> {
+        let position = self.position();
+        let (_, token) = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| syntax_error(position, "unexpected end of expression"))?;
+        self.pos += 1;
+
+        match token {
+            Token::LParen => {
+                let inner = self.or()?;
+                if self.peek() != Some(&Token::RParen) {
+                    return Err(syntax_error(self.position(), "missing ')'"));
+                }
+                self.pos += 1;
+                Ok(Operand::Group(Box::new(inner)))
+            }
+            Token::Str(value) | Token::Number(value) => Ok(Operand::Literal(value)),
+            Token::QuotedIdent(name) => Ok(Operand::Column(name)),
+            Token::Ident(name) if name.eq_ignore_ascii_case("null") => Ok(Operand::Null),
+            Token::Ident(name)
+                if ["and", "or", "not", "is", "like"]
+                    .iter()
+                    .any(|k| name.eq_ignore_ascii_case(k)) =>
+            {
+                Err(syntax_error(
+                    position,
+                    &format!("unexpected keyword '{}'", name),
+                ))
+            }
+            Token::Ident(name) => Ok(Operand::Column(name)),
+            other => Err(syntax_error(
+                position,
+                &format!("unexpected {}", other.describe()),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn columns() -> Vec<String> {
+        ["status", "retries", "email", "note"]
+            .map(String::from)
+            .to_vec()
+    }
+
+    fn row(values: [&str; 4]) -> Vec<String> {
+        values.map(String::from).to_vec()
+    }
+
+    fn eval(expr: &str, values: [&str; 4]) -> bool {
+        let filter: RowFilter = expr.parse().unwrap();
+        filter.check_columns(&columns()).unwrap();
+        filter.matches(&columns(), &row(values))
+    }
+
+    #[test]
+    fn test_comparison_and_boolean_logic() {
+        let failed = ["failed", "5", "a@example.com", ""];
+        assert!(eval(r#"status == "failed" && retries > 3"#, failed));
+        assert!(!eval(r#"status == "failed" && retries > 5"#, failed));
+        assert!(eval(r#"status = 'ok' OR retries >= 5"#, failed));
+        assert!(eval(r#"NOT (status <> "failed")"#, failed));
+        assert!(eval("!(retries < 5)", failed));
+    }
+
+    #[test]
+    fn test_numeric_coercion() {
+        // Textually "10" < "9", numerically it is not
+        assert!(eval("retries > 9", ["ok", "10", "", ""]));
+        assert!(eval("retries == 10.0", ["ok", "10", "", ""]));
+        assert!(eval("retries > -1", ["ok", "0", "", ""]));
+    }
+
+    #[test]
+    fn test_nan_and_infinity() {
+        let nan = ["ok", "NaN", "", ""];
+        assert!(!eval("retries == 5", nan));
+        assert!(!eval("retries <= 0", nan));
+        assert!(!eval("retries > 0", nan));
+        assert!(eval("retries != 7", nan));
+        // Not numbers, so compared as text
+        assert!(eval("retries > 9", ["ok", "inf", "", ""]));
+        assert!(!eval("retries == 'Infinity'", ["ok", "infinity", "", ""]));
+
+        assert_eq!(decimal(" -2.50 "), Some(-2.5));
+        assert_eq!(decimal("1e+3"), Some(1000.0));
+        assert_eq!(decimal(".5"), Some(0.5));
+        for text in ["NaN", "inf", "-Infinity", "1e999", ".", "1e", "0x10", ""] {
+            assert_eq!(decimal(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_null_handling() {
+        let row = ["ok", "", "", "x"];
+        assert!(eval("retries IS NULL", row));
+        assert!(eval("note is not null", row));
+        // Comparisons with NULL are never true
+        assert!(!eval("retries == 0", row));
+        assert!(!eval("retries != 0", row));
+        assert!(!eval("retries == NULL", row));
+    }
+
+    #[test]
+    fn test_like() {
+        let row = ["ok", "1", "bob@example.com", ""];
+        assert!(eval(r#"email LIKE "%@example.com""#, row));
+        assert!(eval(r#"email not like "%@test.com""#, row));
+        assert!(!eval(r#"note LIKE "%""#, row));
+    }
+
+    #[test]
+    fn test_quoted_identifier_and_escaped_quotes() {
+        let columns = vec!["first name".to_string()];
+        let filter: RowFilter = "`first name` == 'O''Brien'".parse().unwrap();
+        assert!(filter.matches(&columns, &["O'Brien".to_string()]));
+    }
+
+    #[test]
+    fn test_syntax_errors_report_position() {
+        let err = "status == ".parse::<RowFilter>().unwrap_err();
+        assert!(err.to_string().contains("unexpected end of expression"));
+
+        let err = "status == 'x' retries".parse::<RowFilter>().unwrap_err();
+        assert!(err.to_string().contains("position 14"));
+
+        let err = "status # 1".parse::<RowFilter>().unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("position 7: unexpected character '#'")
+        );
+
+        assert!("(status == 'x'".parse::<RowFilter>().is_err());
+        assert!("\"open".parse::<RowFilter>().is_err());
+    }
+
+    #[test]
+    fn test_unknown_column() {
+        let filter: RowFilter = "missing > 1".parse().unwrap();
+        let err = filter.check_columns(&columns()).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Unknown column in --where expression: missing")
+        );
+    }
+}
@@ -1,65 +1,126 @@
+use std::fmt;
 use std::io::{self, BufRead, BufReader, Read, Write};
+use std::time::SystemTime;
+
+use cells::CellLimits;
+use columns::Projection;
+use constants::Constant;
+use dictionary::Legend;
+use nesting::{Children, Nesting};
+use profile::ColumnStats;
+use wrap::WrapContext;
+
+#[cfg(feature = "tokio")]
+mod async_convert;
+mod cells;
+mod columns;
+mod constants;
+mod dedupe;
+mod dictionary;
+mod diff;
+mod export;
+mod filter;
+mod metadata;
+mod nesting;
+mod pattern;
+mod profile;
+mod reader;
+mod render;
+mod sample;
+mod stats;
+mod table;
+mod tokenizer;
+#[cfg(feature = "serde")]
+mod typed;
+mod validate;
+mod vertical;
+mod wrap;
+mod writer;
+
+pub use columns::ColumnSelector;
+pub use dedupe::DedupeOrder;
+pub use diff::diff_documents;
+pub use export::SqlExporter;
+pub use filter::RowFilter;
+pub use metadata::{Dialect, SourceInfo};
+pub use profile::ProfileMode;
+pub use reader::{Header, ParseError, Row, ToseReader};
+pub use render::{check_psql_round_trip, render_psql};
+pub use sample::{DEFAULT_SEED, SampleMethod};
+pub use stats::{ConversionStats, FormatStats};
+pub use table::Table;
+pub use tokenizer::TokenCounter;
+#[cfg(feature = "serde")]
+pub use typed::{from_reader, to_writer};
+pub use validate::{RULES, Violation, validate};
+pub use vertical::RecordLayout;
+pub use wrap::WrapTemplate;
+pub use writer::ToseWriter;
 
 /// Represents a parsed psql table
 #[derive(Debug)]
 struct PsqlTable {
     columns: Vec<String>,
     rows: Vec<Vec<String>>,
+    /// Echoed query and timing found around the table
+    source: SourceInfo,
 }
 
 impl PsqlTable {
     /// Parse a psql ASCII table from input
     fn parse<R: Read>(input: R) -> io::Result<Self> {
-        let reader = BufReader::new(input);
-        let lines: Vec<String> = reader.lines().collect::<io::Result<Vec<_>>>()?;
+        let (columns, mut stream) = Self::stream(input)?;
+        let rows = stream.by_ref().collect::<io::Result<Vec<_>>>()?;
+        let source = stream.finish()?;
+        Ok(PsqlTable {
+            columns,
+            rows,
+            source,
+        })
+    }
 
-        if lines.is_empty() {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "Empty input: no data to parse",
-            ));
-        }
+    /// Parse up to the header separator, returning the column names and an
+    /// iterator that parses the data rows as they are read
+    fn stream<R: Read>(input: R) -> io::Result<(Vec<String>, PsqlRows<BufReader<R>>)> {
+        let mut lines = BufReader::new(input).lines();
+        let mut previous: Option<String> = None;
+        let mut echoed: Vec<String> = Vec::new();
 
         // Find the first separator line (contains --- and +)
-        let separator_idx = lines
-            .iter()
-            .position(|line| Self::is_separator_line(line))
-            .ok_or_else(|| {
-                io::Error::new(
-                    io::ErrorKind::InvalidInput,
-                    "Input does not appear to be a psql table (no separator line found)",
-                )
-            })?;
-
-        if separator_idx == 0 {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "Malformed table: separator found at first line (missing header)",
-            ));
-        }
-
-        // The line before the separator is the header
-        let header_line = &lines[separator_idx - 1];
-        let columns = Self::parse_header(header_line)?;
+        loop {
+            let Some(line) = lines.next().transpose()? else {
+                let message = if previous.is_none() {
+                    "Empty input: no data to parse"
+                } else {
+                    "Input does not appear to be a psql table (no separator line found)"
+                };
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+            };
 
-        // Parse data rows (between separator and footer)
-        let mut rows = Vec::new();
-        for line in lines.iter().skip(separator_idx + 1) {
-            // Stop at footer (e.g., "(3 rows)") or another separator
-            if Self::is_footer_line(line) || Self::is_separator_line(line) {
+            if Self::is_separator_line(&line) {
                 break;
             }
-
-            // Skip empty lines
-            if line.trim().is_empty() {
-                continue;
-            }
-
-            let row = Self::parse_row(line, columns.len())?;
-            rows.push(row);
+            echoed.extend(previous.replace(line));
         }
 
-        Ok(PsqlTable { columns, rows })
+        // The line before the separator is the header
+        let header_line = previous.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Malformed table: separator found at first line (missing header)",
+            )
+        })?;
+        let columns = Self::parse_header(&header_line)?;
+
+        let rows = PsqlRows {
+            lines,
+            expected_cols: columns.len(),
+            done: false,
+            query: metadata::echoed_query(&echoed),
+            notices: metadata::notices(&echoed),
+            footer: None,
+        };
+        Ok((columns, rows))
     }
 
     /// Check if a line is a separator (contains ---, optionally with +)
@@ -74,6 +135,12 @@ impl PsqlTable {
         trimmed.starts_with('(') && trimmed.ends_with(')') && trimmed.contains("row")
     }
 
+    /// Row count stated by a footer line
+    fn footer_count(line: &str) -> Option<usize> {
+        let inner = line.trim().strip_prefix('(')?.strip_suffix(')')?;
+        inner.split_whitespace().next()?.parse().ok()
+    }
+
     /// Parse the header line to extract column names
     fn parse_header(line: &str) -> io::Result<Vec<String>> {
         let columns: Vec<String> = line
@@ -119,16 +186,19 @@ impl PsqlTable {
     /// Write the table as CSV
     fn write_csv<W: Write>(&self, mut output: W) -> io::Result<()> {
         for row in &self.rows {
-            let csv_row = row
-                .iter()
-                .map(|cell| Self::escape_csv_field(cell))
-                .collect::<Vec<_>>()
-                .join(",");
-            writeln!(output, "{}", csv_row)?;
+            writeln!(output, "{}", Self::csv_row(row))?;
         }
         Ok(())
     }
 
+    /// Format one row as a CSV line (without the trailing newline)
+    fn csv_row(row: &[String]) -> String {
+        row.iter()
+            .map(|cell| Self::escape_csv_field(cell))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
     /// Escape a field for CSV output (RFC 4180)
     fn escape_csv_field(field: &str) -> String {
         // Empty string means NULL
@@ -152,9 +222,189 @@ impl PsqlTable {
     }
 }
 
+/// Data rows of a psql table, parsed one line at a time
+struct PsqlRows<B> {
+    lines: io::Lines<B>,
+    expected_cols: usize,
+    done: bool,
+    /// Query echoed before the header (`psql -e`)
+    query: Option<String>,
+    /// Status lines and server messages before the header
+    notices: Vec<String>,
+    /// Row count stated by the footer, once reached
+    footer: Option<usize>,
+}
+
+impl<B: BufRead> PsqlRows<B> {
+    /// Read psql's `Time:` line after the footer, once the rows are consumed;
+    /// only blank lines may come between, so the timing of a later query is
+    /// not taken
+    fn finish(&mut self) -> io::Result<SourceInfo> {
+        let mut time = None;
+        if self.done {
+            for line in self.lines.by_ref() {
+                let line = line?;
+                if metadata::is_time_line(&line) {
+                    time = metadata::parse_time(&line);
+                }
+                if !line.trim().is_empty() {
+                    break;
+                }
+            }
+        }
+        Ok(SourceInfo {
+            query: self.query.take(),
+            time,
+            footer_count: self.footer,
+            notices: std::mem::take(&mut self.notices),
+            ..SourceInfo::default()
+        })
+    }
+}
+
+impl<B: BufRead> Iterator for PsqlRows<B> {
+    type Item = io::Result<Vec<String>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(err) => return Some(Err(err)),
+            };
+
+            // Stop at footer (e.g., "(3 rows)") or another separator
+            if PsqlTable::is_footer_line(&line) || PsqlTable::is_separator_line(&line) {
+                self.footer = PsqlTable::footer_count(&line);
+                self.done = true;
+                break;
+            }
+
+            // Skip empty lines
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            return Some(PsqlTable::parse_row(&line, self.expected_cols));
+        }
+        None
+    }
+}
+
+/// Row count shown in the schema header
+///
+/// Renders as `N` for a complete result, `N of TOTAL` when only part of the
+/// result is included in the data block, and appends `, sample=METHOD` when
+/// the included rows were sampled rather than taken from the start,
+/// `, deduped from N` when identical rows were collapsed, `, nested=ENTITY`
+/// when child blocks follow each record, `, vertical` when the data block is
+/// transposed, and `, dict=COL+COL` when columns are dictionary-encoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RowCount {
+    shown: usize,
+    total: usize,
+    sample: Option<SampleMethod>,
+    truncated: bool,
+    /// Number of rows before duplicates were collapsed
+    deduped: Option<usize>,
+    /// Entity name of the child blocks under each record
+    nested: Option<String>,
+    /// Data block holds one line per column instead of one per record
+    vertical: bool,
+    dictionary: Vec<String>,
+}
+
+impl RowCount {
+    fn is_partial(&self) -> bool {
+        self.shown < self.total
+    }
+}
+
+impl From<usize> for RowCount {
+    fn from(count: usize) -> Self {
+        RowCount {
+            shown: count,
+            total: count,
+            sample: None,
+            truncated: false,
+            deduped: None,
+            nested: None,
+            vertical: false,
+            dictionary: Vec::new(),
+        }
+    }
+}
+
+impl fmt::Display for RowCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_partial() {
+            write!(f, "{} of {}", self.shown, self.total)?;
+        } else {
+            write!(f, "{}", self.shown)?;
+        }
+        if let Some(method) = &self.sample {
+            write!(f, ", sample={}", method)?;
+        }
+        if let Some(original) = self.deduped {
+            write!(f, ", deduped from {}", original)?;
+        }
+        if let Some(entity) = &self.nested {
+            write!(f, ", nested={}", entity)?;
+        }
+        if self.vertical {
+            write!(f, ", vertical")?;
+        }
+        if !self.dictionary.is_empty() {
+            write!(f, ", dict={}", self.dictionary.join("+"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Everything written around the data rows of a document
+#[derive(Debug, Clone)]
+struct Layout {
+    count: RowCount,
+    /// Dictionary legends, written between the header and the data block
+    legends: Vec<Legend>,
+    /// Columns folded out of the data block, listed after the field list
+    constants: Vec<Constant>,
+    /// Column statistics records, written as a second entity
+    profile: Option<Vec<Vec<String>>>,
+    /// Child rows written under each record when grouping
+    children: Option<Children>,
+}
+
 /// Core TOSE converter that transforms psql table data into TOSE format
+#[derive(Debug, Clone)]
 pub struct ToseConverter {
     entity_name: String,
+    preamble: Option<String>,
+    max_rows: Option<usize>,
+    max_tokens: Option<usize>,
+    token_counter: TokenCounter,
+    sample: Option<(SampleMethod, usize)>,
+    cell_limits: CellLimits,
+    projection: Projection,
+    filter: Option<RowFilter>,
+    dictionary: Option<usize>,
+    fold_constants: bool,
+    dedupe: Option<DedupeOrder>,
+    profile: Option<ProfileMode>,
+    summary: Option<(usize, u64)>,
+    nesting: Option<Nesting>,
+    record_layout: RecordLayout,
+    /// Vertical layout applies up to this many rows...
+    vertical_max_rows: usize,
+    /// ...or from this many columns
+    vertical_min_columns: usize,
+    wrap: Option<WrapTemplate>,
+    source_query: Option<String>,
+    metadata: bool,
+    source: Option<String>,
+    generated: Option<String>,
+    /// Set on the per-entity converters of a multi-entity document, whose
+    /// document-wide metadata is written once at the top
+    in_document: bool,
 }
 
 impl ToseConverter {
@@ -162,32 +412,914 @@ impl ToseConverter {
     pub fn new() -> Self {
         ToseConverter {
             entity_name: "result".to_string(),
+            preamble: None,
+            max_rows: None,
+            max_tokens: None,
+            token_counter: TokenCounter::heuristic(),
+            sample: None,
+            cell_limits: CellLimits::default(),
+            projection: Projection::default(),
+            filter: None,
+            dictionary: None,
+            fold_constants: false,
+            dedupe: None,
+            profile: None,
+            summary: None,
+            nesting: None,
+            record_layout: RecordLayout::Table,
+            vertical_max_rows: 1,
+            vertical_min_columns: 20,
+            wrap: None,
+            source_query: None,
+            metadata: false,
+            source: None,
+            generated: None,
+            in_document: false,
+        }
+    }
+
+    /// Entity name written in the schema header (letters, digits and `_`)
+    pub fn with_entity_name(mut self, name: impl Into<String>) -> Self {
+        self.entity_name = name.into();
+        self
+    }
+
+    /// Text written at the top of multi-entity documents, e.g. a note on how
+    /// the entities relate
+    pub fn with_preamble(mut self, text: impl Into<String>) -> Self {
+        self.preamble = Some(text.into());
+        self
+    }
+
+    /// Emit at most `max_rows` data rows
+    pub fn with_max_rows(mut self, max_rows: usize) -> Self {
+        self.max_rows = Some(max_rows);
+        self
+    }
+
+    /// Stop emitting rows once the document would exceed `max_tokens`
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// Emit a sample of `size` rows chosen by `method` instead of the full result
+    pub fn with_sample(mut self, method: SampleMethod, size: usize) -> Self {
+        self.sample = Some((method, size));
+        self
+    }
+
+    /// Cut cells longer than `max_chars` characters, ending them with `…[+N chars]`
+    pub fn with_max_cell_chars(mut self, max_chars: usize) -> Self {
+        self.cell_limits.max_chars = Some(max_chars);
+        self
+    }
+
+    /// Override the cell length limit for one column
+    pub fn with_column_max_chars(mut self, column: impl Into<String>, max_chars: usize) -> Self {
+        self.cell_limits.column_max_chars.insert(column.into(), max_chars);
+        self
+    }
+
+    /// Replace hex `bytea` values (`\x...`) with their length and hash
+    pub fn with_blob_summaries(mut self, summarize: bool) -> Self {
+        self.cell_limits.summarize_blobs = summarize;
+        self
+    }
+
+    /// Keep only the rows matching `filter`; the header counts the matching rows
+    pub fn with_filter(mut self, filter: RowFilter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Emit only the selected columns, in selector order
+    ///
+    /// Selectors, like every other option, refer to the source column names.
+    pub fn with_columns(mut self, selectors: Vec<ColumnSelector>) -> Self {
+        self.projection.include = Some(selectors);
+        self
+    }
+
+    /// Drop the columns matched by any of `selectors`
+    pub fn with_excluded_columns(mut self, selectors: Vec<ColumnSelector>) -> Self {
+        self.projection.exclude.extend(selectors);
+        self
+    }
+
+    /// Emit column `old` under the name `new`
+    pub fn with_rename(mut self, old: impl Into<String>, new: impl Into<String>) -> Self {
+        self.projection.renames.push((old.into(), new.into()));
+        self
+    }
+
+    /// Replace values of text columns with at most `max_distinct` distinct
+    /// values by short codes, listing each code once in a legend line
+    pub fn with_dictionary_encoding(mut self, max_distinct: usize) -> Self {
+        self.dictionary = Some(max_distinct);
+        self
+    }
+
+    /// Move columns that are NULL or hold one value in every row out of the
+    /// data block and into the header, e.g. `{id,name}|tenant_id=42:`
+    pub fn with_fold_constants(mut self, fold: bool) -> Self {
+        self.fold_constants = fold;
+        self
+    }
+
+    /// Emit each distinct row once with a trailing `_count` column
+    pub fn with_dedupe(mut self, order: DedupeOrder) -> Self {
+        self.dedupe = Some(order);
+        self
+    }
+
+    /// Emit per-column statistics as an `<entity>_stats` entity, after or
+    /// instead of the rows
+    pub fn with_profile(mut self, mode: ProfileMode) -> Self {
+        self.profile = Some(mode);
+        self
+    }
+
+    /// Nest rows under their parent: each distinct `keys` value is written once
+    /// with the key and `parents` columns, followed by an indented
+    /// `items[N]{...}:` block of the remaining columns
+    ///
+    /// Column names are the source names, as for every other option. Dictionary
+    /// encoding cannot be combined with nesting.
+    pub fn with_group_by(mut self, keys: Vec<String>, parents: Vec<String>) -> Self {
+        let child_entity = match self.nesting.take() {
+            Some(nesting) => nesting.child_entity,
+            None => "items".to_string(),
+        };
+        self.nesting = Some(Nesting {
+            keys,
+            parents,
+            child_entity,
+        });
+        self
+    }
+
+    /// Entity name of the child blocks written by
+    /// [`with_group_by`](Self::with_group_by) (default `items`)
+    pub fn with_child_entity(mut self, name: impl Into<String>) -> Self {
+        if let Some(nesting) = &mut self.nesting {
+            nesting.child_entity = name.into();
         }
+        self
+    }
+
+    /// Choose between one line per record and one line per column
+    pub fn with_layout(mut self, layout: RecordLayout) -> Self {
+        self.record_layout = layout;
+        self
+    }
+
+    /// [`RecordLayout::Vertical`] transposes results with at most this many
+    /// rows (default 1)
+    pub fn with_vertical_max_rows(mut self, max_rows: usize) -> Self {
+        self.vertical_max_rows = max_rows;
+        self
+    }
+
+    /// [`RecordLayout::Vertical`] transposes results with at least this many
+    /// columns (default 20)
+    pub fn with_vertical_min_columns(mut self, min_columns: usize) -> Self {
+        self.vertical_min_columns = min_columns;
+        self
+    }
+
+    /// Write the output inside `template`, e.g. a markdown fence or XML tags
+    pub fn with_wrap(mut self, template: WrapTemplate) -> Self {
+        self.wrap = Some(template);
+        self
+    }
+
+    /// Query that produced the input, for the `{query}` wrap placeholder and
+    /// `# query:` metadata (defaults to the query echoed by `psql -e`)
+    pub fn with_source_query(mut self, query: impl Into<String>) -> Self {
+        self.source_query = Some(query.into());
+        self
+    }
+
+    /// Write `# key: value` metadata lines before the schema header: the TOSE
+    /// version, source, generation time, query, psql timing and truncation
+    pub fn with_metadata(mut self, metadata: bool) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Description of the database the result came from, for `# source:`
+    pub fn with_source(mut self, source: impl Into<String>) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+
+    /// Generation time written as `# generated:` (ISO 8601, UTC)
+    pub fn with_generated_at(mut self, time: SystemTime) -> Self {
+        self.generated = Some(metadata::format_timestamp(time));
+        self
+    }
+
+    /// Stream through the input without buffering it, writing only the real
+    /// row count, `sample_rows` reservoir-sampled rows (seeded with `seed`)
+    /// and column statistics
+    ///
+    /// Filtering and column projection still apply; other options are ignored.
+    pub fn with_summary(mut self, sample_rows: usize, seed: u64) -> Self {
+        self.summary = Some((sample_rows, seed));
+        self
+    }
+
+    /// Token counter used for the `max_tokens` budget (chars/4 by default)
+    pub fn with_token_counter(mut self, counter: TokenCounter) -> Self {
+        self.token_counter = counter;
+        self
     }
 
     /// Convert psql table data from input stream to TOSE format on output stream
-    pub fn convert<R: Read, W: Write>(&self, input: R, mut output: W) -> io::Result<()> {
+    pub fn convert<R: Read, W: Write>(&self, input: R, output: W) -> io::Result<()> {
+        check_entity_name(&self.entity_name)?;
+        match &self.wrap {
+            None => self.convert_unwrapped(input, output).map(drop),
+            Some(_) => {
+                let mut body = Vec::new();
+                let context = self.convert_unwrapped(input, &mut body)?;
+                self.write_wrapped(&context, &body, output)
+            }
+        }
+    }
+
+    /// Tokens the wrap template adds around the document, counted with every
+    /// row shown
+    fn wrap_tokens(&self, table: &PsqlTable, layout: &Layout) -> usize {
+        let Some(template) = &self.wrap else {
+            return 0;
+        };
+        let context = WrapContext {
+            entity: self.entity_name.clone(),
+            rows: layout.count.total,
+            total: layout.count.total,
+            query: self.query(&table.source),
+            columns: table.columns.clone(),
+        };
+        self.token_counter.count(&template.render(&context, ""))
+    }
+
+    /// Convert without the wrap template, returning what the template may use
+    fn convert_unwrapped<R: Read, W: Write>(&self, input: R, mut output: W) -> io::Result<WrapContext> {
+        if let Some((sample_rows, seed)) = self.summary {
+            return self.summarize(input, output, sample_rows, seed);
+        }
+
         // Parse the psql table
-        let table = PsqlTable::parse(input)?;
+        let mut table = PsqlTable::parse(input)?;
+        let layout = self.transform(&mut table)?;
+        self.write_table(&table, &layout, &mut output)?;
+        Ok(WrapContext {
+            entity: self.entity_name.clone(),
+            rows: layout.count.shown,
+            total: layout.count.total,
+            query: self.query(&table.source),
+            columns: table.columns,
+        })
+    }
+
+    /// Write `body` inside the wrap template, or as is without one
+    fn write_wrapped<W: Write>(&self, context: &WrapContext, body: &[u8], mut output: W) -> io::Result<()> {
+        match &self.wrap {
+            Some(template) => {
+                let body = String::from_utf8_lossy(body);
+                let wrapped = template.render(context, &body);
+                output.write_all(wrapped.as_bytes())
+            }
+            None => output.write_all(body),
+        }
+    }
+
+    /// Convert like [`convert`](Self::convert), then measure the output against
+    /// JSON, JSONL, CSV and the original psql text
+    pub fn convert_with_stats<R: Read, W: Write>(
+        &self,
+        mut input: R,
+        mut output: W,
+        counter: &TokenCounter,
+    ) -> io::Result<ConversionStats> {
+        check_entity_name(&self.entity_name)?;
+        if self.summary.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Conversion statistics are not available in summary mode",
+            ));
+        }
+
+        // Keep the raw input so the psql text itself can be measured
+        let mut psql = String::new();
+        input.read_to_string(&mut psql)?;
+        let mut table = PsqlTable::parse(psql.as_bytes())?;
+        let layout = self.transform(&mut table)?;
+
+        let mut tose = Vec::new();
+        self.write_table(&table, &layout, &mut tose)?;
+        let context = WrapContext {
+            entity: self.entity_name.clone(),
+            rows: layout.count.shown,
+            total: layout.count.total,
+            query: self.query(&table.source),
+            columns: table.columns.clone(),
+        };
+        self.write_wrapped(&context, &tose, &mut output)?;
+
+        let tose = String::from_utf8_lossy(&tose);
+        Ok(ConversionStats::measure(
+            counter,
+            &tose,
+            &psql,
+            &table.columns,
+            &table.rows,
+        ))
+    }
+
+    /// Convert several psql results into one document, each under its own
+    /// entity name
+    ///
+    /// The document starts with an `@document[N]{name,...}:` line listing all
+    /// N entities (including `_stats` entities), followed by the preamble as
+    /// `> ` lines, then each entity after a blank line.
+    pub fn convert_document<R: Read, W: Write>(
+        &self,
+        inputs: Vec<(String, R)>,
+        output: W,
+    ) -> io::Result<()> {
+        let mut names: Vec<String> = Vec::new();
+        let mut blocks = Vec::new();
+        let mut context = WrapContext::default();
+        for (name, input) in inputs {
+            let mut converter = self.clone().with_entity_name(name);
+            converter.in_document = true;
+            for entity in converter.entity_names() {
+                if names.contains(&entity) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("Duplicate entity name in document: {}", entity),
+                    ));
+                }
+                names.push(entity);
+            }
+            check_entity_name(&converter.entity_name)?;
+            let mut block = Vec::new();
+            let entity = converter.convert_unwrapped(input, &mut block)?;
+            context.rows += entity.rows;
+            context.total += entity.total;
+            blocks.push(block);
+        }
+        context.entity = names.join(",");
+
+        let mut document = self.document_metadata().into_bytes();
+        writeln!(document, "@document[{}]{{{}}}:", names.len(), names.join(","))?;
+        if let Some(preamble) = &self.preamble {
+            for line in preamble.lines() {
+                writeln!(document, "> {}", line)?;
+            }
+        }
+        for block in blocks {
+            document.push(b'\n');
+            document.extend(block);
+        }
+
+        self.write_wrapped(&context, &document, output)
+    }
+
+    /// Convert psql output holding several results (e.g. from
+    /// `psql -c "SELECT ...; SELECT ..."`) into one document
+    ///
+    /// Results are named from `names` in order; any beyond it are named
+    /// `result1`, `result2`, ... by position.
+    pub fn convert_results<R: Read, W: Write>(
+        &self,
+        mut input: R,
+        names: &[String],
+        output: W,
+    ) -> io::Result<()> {
+        let mut text = String::new();
+        input.read_to_string(&mut text)?;
+        let results = split_results(&text);
+        if results.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Empty input: no data to parse",
+            ));
+        }
+
+        let inputs = results
+            .into_iter()
+            .enumerate()
+            .map(|(idx, result)| {
+                let name = names.get(idx).cloned().unwrap_or_else(|| format!("result{}", idx + 1));
+                (name, result.as_bytes())
+            })
+            .collect();
+        self.convert_document(inputs, output)
+    }
+
+    /// Names of the entities [`convert`](Self::convert) writes, in order
+    fn entity_names(&self) -> Vec<String> {
+        let stats = format!("{}_stats", self.entity_name);
+        match (self.summary, self.profile) {
+            (Some(_), _) | (None, Some(ProfileMode::WithRows)) => {
+                vec![self.entity_name.clone(), stats]
+            }
+            (None, Some(ProfileMode::Only)) => vec![stats],
+            (None, None) => vec![self.entity_name.clone()],
+        }
+    }
+
+    /// Write a sample and column statistics while streaming through the rows
+    fn summarize<R: Read, W: Write>(
+        &self,
+        input: R,
+        mut output: W,
+        sample_rows: usize,
+        seed: u64,
+    ) -> io::Result<WrapContext> {
+        let (source_columns, mut stream) = PsqlTable::stream(input)?;
+        if let Some(filter) = &self.filter {
+            filter.check_columns(&source_columns)?;
+        }
+        let selected = self.projection.select(&source_columns)?;
+
+        let mut stats: Vec<ColumnStats> = selected.iter().map(|_| ColumnStats::default()).collect();
+        let mut total = 0;
+        let mut error = None;
+        let rows = stream
+            .by_ref()
+            .map_while(|row| row.map_err(|err| error = Some(err)).ok())
+            .filter(|row| {
+                self.filter
+                    .as_ref()
+                    .is_none_or(|filter| filter.matches(&source_columns, row))
+            })
+            .map(|mut row| {
+                total += 1;
+                let row: Vec<String> = selected
+                    .iter()
+                    .map(|(idx, _)| std::mem::take(&mut row[*idx]))
+                    .collect();
+                for (column, value) in stats.iter_mut().zip(&row) {
+                    column.add(value);
+                }
+                row
+            });
+        let sampled = sample::sample_stream(rows, sample_rows, seed);
+        if let Some(err) = error {
+            return Err(err);
+        }
+        let source = stream.finish()?;
+
+        let mut count = RowCount::from(total);
+        count.shown = sampled.len();
+        if count.is_partial() {
+            count.sample = Some(SampleMethod::Reservoir { seed });
+        }
+        let table = PsqlTable {
+            columns: selected.into_iter().map(|(_, name)| name).collect(),
+            rows: sampled,
+            source,
+        };
+        let records: Vec<Vec<String>> = table
+            .columns
+            .iter()
+            .zip(&stats)
+            .map(|(name, column)| column.record(name))
+            .collect();
+
+        let context = WrapContext {
+            entity: self.entity_name.clone(),
+            rows: count.shown,
+            total: count.total,
+            query: self.query(&table.source),
+            columns: table.columns.clone(),
+        };
+        output.write_all(self.metadata_lines(&count, &table.source).as_bytes())?;
+        output.write_all(self.generate_header(count, &table.columns).as_bytes())?;
+        table.write_csv(&mut output)?;
+        output.write_all(b"\n")?;
+        output.write_all(self.profile_document(&records).as_bytes())?;
+        Ok(context)
+    }
+
+    /// Apply filtering, sampling, cell limits, column projection, deduplication,
+    /// nesting, constant folding, dictionary encoding and row limits, returning
+    /// what to write around the rows
+    fn transform(&self, table: &mut PsqlTable) -> io::Result<Layout> {
+        if self.nesting.is_some() && self.dictionary.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Dictionary encoding cannot be combined with --group-by",
+            ));
+        }
+        if let Some(nesting) = &self.nesting {
+            check_entity_name(&nesting.child_entity)?;
+        }
+        if let Some(filter) = &self.filter {
+            filter.check_columns(&table.columns)?;
+            let columns = &table.columns;
+            table.rows.retain(|row| filter.matches(columns, row));
+        }
+
+        let mut count = RowCount::from(table.row_count());
+
+        // Statistics describe every matching row, before sampling or limits
+        let profile = match self.profile {
+            Some(_) => {
+                let stats = profile::profile(&table.columns, &table.rows);
+                let selected = self.projection.select(&table.columns)?;
+                Some(
+                    selected
+                        .into_iter()
+                        .map(|(idx, name)| {
+                            let mut record = stats[idx].clone();
+                            record[0] = name;
+                            record
+                        })
+                        .collect(),
+                )
+            }
+            None => None,
+        };
+
+        if let Some((method, size)) = &self.sample {
+            // Checked even when every row fits, so a typo is never silently ignored
+            method.check_columns(&table.columns)?;
+            if table.row_count() > *size {
+                let rows = std::mem::take(&mut table.rows);
+                table.rows = sample::sample_rows(rows, &table.columns, method, *size)?;
+                count.sample = Some(method.clone());
+            }
+        }
+
+        self.cell_limits.apply(&table.columns, &mut table.rows)?;
+        self.projection.apply(&mut table.columns, &mut table.rows)?;
+
+        if let Some(order) = self.dedupe {
+            let original = table.row_count();
+            dedupe::dedupe(&mut table.columns, &mut table.rows, order)?;
+            count.deduped = Some(original);
+            if count.sample.is_none() {
+                count.total = table.row_count();
+            }
+        }
+
+        let children = match &self.nesting {
+            Some(nesting) => {
+                // Keys name source columns; find them under their output names
+                let output_names = |names: &[String]| {
+                    names
+                        .iter()
+                        .map(|name| self.projection.output_name(name))
+                        .collect()
+                };
+                let nesting = Nesting {
+                    keys: output_names(&nesting.keys),
+                    parents: output_names(&nesting.parents),
+                    child_entity: nesting.child_entity.clone(),
+                };
+                let children = nesting.apply(&mut table.columns, &mut table.rows)?;
+                count.total = table.row_count();
+                count.nested = Some(children.entity.clone());
+                Some(children)
+            }
+            None => None,
+        };
+
+        let constants = if self.fold_constants {
+            constants::fold(&mut table.columns, &mut table.rows)
+        } else {
+            Vec::new()
+        };
+
+        let legends = match self.dictionary {
+            Some(max_distinct) => dictionary::encode(&table.columns, &mut table.rows, max_distinct),
+            None => Vec::new(),
+        };
+        count.dictionary = legends.iter().map(|l| l.column.clone()).collect();
+        let mut layout = Layout {
+            count,
+            legends,
+            constants,
+            profile,
+            children,
+        };
+
+        let sampled = table.row_count();
+        if let Some(max_rows) = self.max_rows {
+            table.rows.truncate(max_rows);
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            let budget = match &layout.profile {
+                Some(stats) => max_tokens.saturating_sub(
+                    self.token_counter.count(&format!("\n{}", self.profile_document(stats))),
+                ),
+                None => max_tokens,
+            };
+            let wrap_tokens = self.wrap_tokens(table, &layout);
+            if wrap_tokens >= max_tokens {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "The --wrap template alone takes {} tokens, over --max-tokens {}",
+                        wrap_tokens, max_tokens
+                    ),
+                ));
+            }
+            let budget = budget.saturating_sub(wrap_tokens);
+            let keep = self.rows_within_budget(table, &layout, sampled, budget);
+            table.rows.truncate(keep);
+        }
+
+        layout.count.shown = table.row_count();
+        layout.count.truncated = layout.count.shown < sampled;
+        layout.count.vertical = self.use_vertical(table, &layout);
+        Ok(layout)
+    }
+
+    /// Whether to transpose the data block, given the rows that will be written
+    fn use_vertical(&self, table: &PsqlTable, layout: &Layout) -> bool {
+        // Child blocks belong under table records
+        if table.row_count() == 0 || layout.children.is_some() {
+            return false;
+        }
+
+        let tokens = |vertical: bool| {
+            let mut candidate = layout.clone();
+            candidate.count.vertical = vertical;
+            let mut rendered = Vec::new();
+            self.write_rows(table, &candidate, &mut rendered)
+                .map(|()| self.token_counter.count(&String::from_utf8_lossy(&rendered)))
+                .unwrap_or(usize::MAX)
+        };
+
+        match self.record_layout {
+            RecordLayout::Table => false,
+            RecordLayout::Vertical => {
+                let eligible = table.row_count() <= self.vertical_max_rows
+                    || table.columns.len() >= self.vertical_min_columns;
+                // Rows were cut to fit the budget as a table; stay within it
+                eligible && self.max_tokens.is_none_or(|budget| tokens(true) <= budget)
+            }
+            RecordLayout::Auto => tokens(true) < tokens(false),
+        }
+    }
+
+    /// Number of leading rows that fit in `budget` tokens, header and marker included
+    fn rows_within_budget(
+        &self,
+        table: &PsqlTable,
+        layout: &Layout,
+        available: usize,
+        budget: usize,
+    ) -> usize {
+        let line_tokens = |line: String| self.token_counter.count(&line);
+        let count = &layout.count;
+
+        // Everything that is always emitted, sized for the worst case
+        let legends: usize = layout.legends.iter().map(|l| line_tokens(l.line())).sum();
+        let mut full = count.clone();
+        full.shown = table.row_count();
+        let complete = legends
+            + line_tokens(self.metadata_lines(&full, &table.source))
+            + line_tokens(self.layout_header(full, &table.columns, layout));
+        let mut cut = count.clone();
+        cut.shown = count.total.saturating_sub(1);
+        let partial = legends
+            + line_tokens(self.metadata_lines(&cut, &table.source))
+            + line_tokens(self.layout_header(cut, &table.columns, layout))
+            + line_tokens(Self::truncation_marker(count.total));
+
+        let row_tokens: Vec<usize> = table
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(idx, row)| line_tokens(Self::record(row, idx, layout)))
+            .collect();
+
+        // If every remaining row fits without a marker, nothing is cut
+        if table.row_count() == available && complete + row_tokens.iter().sum::<usize>() <= budget {
+            return table.row_count();
+        }
+
+        let mut used = partial;
+        row_tokens
+            .iter()
+            .take_while(|&&tokens| {
+                used += tokens;
+                used <= budget
+            })
+            .count()
+    }
+
+    /// Write a table as a TOSE document
+    fn write_table<W: Write>(&self, table: &PsqlTable, layout: &Layout, mut output: W) -> io::Result<()> {
+        if self.profile != Some(ProfileMode::Only) {
+            self.write_rows(table, layout, &mut output)?;
+        }
+
+        if let Some(stats) = &layout.profile {
+            if self.profile == Some(ProfileMode::WithRows) {
+                output.write_all(b"\n")?;
+            }
+            output.write_all(self.profile_document(stats).as_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the header, legends and data block of the result entity
+    fn write_rows<W: Write>(&self, table: &PsqlTable, layout: &Layout, mut output: W) -> io::Result<()> {
+        let count = &layout.count;
+
+        output.write_all(self.metadata_lines(count, &table.source).as_bytes())?;
 
         // Generate and write the TOSE header
-        let header = self.generate_header(table.row_count(), &table.columns);
+        let fields = if count.vertical {
+            vertical::fields(table.row_count())
+        } else {
+            table.columns.clone()
+        };
+        let header = self.layout_header(count.clone(), &fields, layout);
         output.write_all(header.as_bytes())?;
 
-        // Write the table as CSV
-        table.write_csv(&mut output)?;
+        // Legends for dictionary-encoded columns, in header order
+        for legend in &layout.legends {
+            output.write_all(legend.line().as_bytes())?;
+        }
+
+        // Write the table as CSV, with each record's children when nesting
+        if count.vertical {
+            for line in vertical::transpose(&table.columns, &table.rows) {
+                writeln!(output, "{}", PsqlTable::csv_row(&line))?;
+            }
+        } else if layout.children.is_some() {
+            for (idx, row) in table.rows.iter().enumerate() {
+                output.write_all(Self::record(row, idx, layout).as_bytes())?;
+            }
+        } else {
+            table.write_csv(&mut output)?;
+        }
+
+        // Tell the reader the data block is incomplete
+        if count.truncated {
+            output.write_all(Self::truncation_marker(count.total - count.shown).as_bytes())?;
+        }
 
         Ok(())
     }
 
+    /// Query for `# query:` and `{query}`: the configured one, else the echoed one
+    fn query(&self, source: &SourceInfo) -> Option<String> {
+        self.source_query.clone().or_else(|| source.query.clone())
+    }
+
+    /// Document-wide metadata lines: TOSE version, source and generation time
+    fn document_metadata(&self) -> String {
+        let mut lines = String::new();
+        if self.metadata {
+            lines.push_str(&format!("# tose:{}\n", metadata::TOSE_VERSION));
+            if let Some(source) = &self.source {
+                lines.push_str(&metadata::line("source", source));
+            }
+            if let Some(generated) = &self.generated {
+                lines.push_str(&metadata::line("generated", generated));
+            }
+        }
+        lines
+    }
+
+    /// Metadata lines written before an entity's schema header
+    fn metadata_lines(&self, count: &RowCount, source: &SourceInfo) -> String {
+        if !self.metadata {
+            return String::new();
+        }
+
+        let mut lines = if self.in_document {
+            String::new()
+        } else {
+            self.document_metadata()
+        };
+        if let Some(query) = self.query(source) {
+            lines.push_str(&metadata::line("query", &query));
+        }
+        if let Some(time) = &source.time {
+            lines.push_str(&metadata::line("time", time));
+        }
+        if count.is_partial() {
+            let truncated = format!("{} of {} rows", count.shown, count.total);
+            lines.push_str(&metadata::line("truncated", &truncated));
+        }
+        lines
+    }
+
+    /// Format one record of the data block, followed by its child block
+    fn record(row: &[String], idx: usize, layout: &Layout) -> String {
+        let mut record = PsqlTable::csv_row(row) + "\n";
+        if let Some(children) = &layout.children {
+            record.push_str(&children.block(idx, PsqlTable::csv_row));
+        }
+        record
+    }
+
+    /// Format column statistics as an `<entity>_stats` document
+    fn profile_document(&self, stats: &[Vec<String>]) -> String {
+        let mut document = format!(
+            "{}_stats[{}]{{{}}}:\n",
+            self.entity_name,
+            stats.len(),
+            profile::FIELDS.join(",")
+        );
+        for record in stats {
+            document.push_str(&PsqlTable::csv_row(record));
+            document.push('\n');
+        }
+        document
+    }
+
     /// Generate the TOSE schema header
-    fn generate_header(&self, row_count: usize, columns: &[String]) -> String {
+    fn generate_header(&self, row_count: impl Into<RowCount>, columns: &[String]) -> String {
         let field_list = columns.join(",");
         format!(
             "{}[{}]{{{}}}:\n",
-            self.entity_name, row_count, field_list
+            self.entity_name, row_count.into(), field_list
         )
     }
+
+    /// Generate the schema header, followed by any folded constant columns
+    fn layout_header(&self, row_count: RowCount, columns: &[String], layout: &Layout) -> String {
+        let header = self.generate_header(row_count, columns);
+        if layout.constants.is_empty() {
+            return header;
+        }
+
+        let constants: Vec<String> = layout.constants.iter().map(|c| c.to_string()).collect();
+        let fields_end = header.len() - ":\n".len();
+        format!("{}|{}:\n", &header[..fields_end], constants.join(","))
+    }
+
+    /// Trailing line written after the data block of a truncated result
+    fn truncation_marker(omitted: usize) -> String {
+        format!("# truncated: {} more rows not shown\n", omitted)
+    }
+}
+
+/// Reject entity names the schema header cannot carry
+fn check_entity_name(name: &str) -> io::Result<()> {
+    check_name("entity", name)
+}
+
+/// Reject column names the field list cannot carry
+fn check_column_name(name: &str) -> io::Result<()> {
+    check_name("column", name)
+}
+
+fn check_name(kind: &str, name: &str) -> io::Result<()> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid {} name: {:?} (use letters, digits and _)", kind, name),
+        ));
+    }
+    Ok(())
+}
+
+/// Split psql output holding several results after each `(N rows)` footer,
+/// dropping text between results that holds no table
+fn split_results(text: &str) -> Vec<&str> {
+    let mut results = Vec::new();
+    let mut start = 0;
+    let mut offset = 0;
+    let mut after_footer = false;
+    for line in text.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+        if PsqlTable::is_footer_line(line) {
+            results.push(&text[start..offset]);
+            start = offset;
+            after_footer = true;
+        } else if after_footer && (line.trim().is_empty() || metadata::is_time_line(line)) {
+            // psql's timing report belongs to the result above it
+            if let Some(last) = results.last_mut() {
+                let last_start = line_start - last.len();
+                *last = &text[last_start..offset];
+            }
+            start = offset;
+        } else {
+            after_footer = false;
+        }
+    }
+
+    let rest = &text[start..];
+    if rest.lines().any(PsqlTable::is_separator_line) {
+        results.push(rest);
+    }
+    results
 }
 
 impl Default for ToseConverter {
@@ -322,4 +1454,22 @@ mod tests {
         let header = converter.generate_header(42, &columns);
         assert_eq!(header, "result[42]{id,name,email}:\n");
     }
+
+    #[test]
+    fn test_generate_partial_header() {
+        let converter = ToseConverter::new();
+        let columns = vec!["id".to_string(), "name".to_string()];
+        let count = RowCount {
+            shown: 50,
+            total: 12034,
+            sample: None,
+            truncated: true,
+            deduped: None,
+            nested: None,
+            vertical: false,
+            dictionary: Vec::new(),
+        };
+        let header = converter.generate_header(count, &columns);
+        assert_eq!(header, "result[50 of 12034]{id,name}:\n");
+    }
 }
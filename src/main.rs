@@ -1,14 +1,552 @@
-use std::io::{self, BufWriter};
-use tose_converter::ToseConverter;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::SystemTime;
+use tose_converter::{
+    ColumnSelector, DEFAULT_SEED, DedupeOrder, ProfileMode, RULES, RecordLayout, RowFilter,
+    SampleMethod, SqlExporter, TokenCounter, ToseConverter, WrapTemplate, check_psql_round_trip,
+    diff_documents, render_psql,
+};
+
+/// Rows kept by `--sample` when `--sample-size` is not given
+const DEFAULT_SAMPLE_SIZE: usize = 100;
+
+/// Sample rows shown by `--summary` when `--sample-size` is not given
+const DEFAULT_SUMMARY_ROWS: usize = 5;
+
+/// Most distinct values a column may have for `--dict` to encode it
+const DEFAULT_DICT_MAX_DISTINCT: usize = 32;
+
+/// Exit status of `validate` when a document breaks a rule
+const EXIT_INVALID: u8 = 1;
+
+/// Exit status of `validate` when a document cannot be read, or for bad usage
+const EXIT_UNREADABLE: u8 = 2;
+
+/// Command-line options (all optional; bare positional arguments are ignored)
+#[derive(Default)]
+struct Options {
+    stats: bool,
+    vocab: Option<PathBuf>,
+    max_rows: Option<usize>,
+    max_tokens: Option<usize>,
+    sample: Option<SampleMethod>,
+    sample_size: Option<usize>,
+    seed: Option<u64>,
+    max_cell_chars: Option<usize>,
+    column_max_chars: Vec<(String, usize)>,
+    summarize_blobs: bool,
+    columns: Option<Vec<ColumnSelector>>,
+    exclude: Vec<ColumnSelector>,
+    renames: Vec<(String, String)>,
+    filter: Option<RowFilter>,
+    dict: bool,
+    dict_max_distinct: Option<usize>,
+    fold_constants: bool,
+    dedupe: Option<DedupeOrder>,
+    profile: Option<ProfileMode>,
+    summary: bool,
+    group_by: Vec<String>,
+    parent_columns: Vec<String>,
+    child_entity: Option<String>,
+    entities: Vec<String>,
+    inputs: Vec<(String, PathBuf)>,
+    multi: bool,
+    preamble: Option<String>,
+    layout: Option<RecordLayout>,
+    vertical_max_rows: Option<usize>,
+    vertical_min_columns: Option<usize>,
+    wrap: Option<WrapTemplate>,
+    query: Option<String>,
+    meta: bool,
+    source: Option<String>,
+}
+
+impl Options {
+    fn parse<I: Iterator<Item = String>>(mut args: I) -> io::Result<Self> {
+        let mut options = Options::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--stats" => options.stats = true,
+                "--vocab" => options.vocab = Some(PathBuf::from(Self::value(&arg, args.next())?)),
+                "--max-rows" => options.max_rows = Some(Self::number(&arg, args.next())?),
+                "--max-tokens" => options.max_tokens = Some(Self::number(&arg, args.next())?),
+                "--sample" => options.sample = Some(Self::value(&arg, args.next())?.parse()?),
+                "--sample-size" => options.sample_size = Some(Self::number(&arg, args.next())?),
+                "--max-cell-chars" => {
+                    // Either a default limit (`200`) or a column override (`body=2000`)
+                    let value = Self::value(&arg, args.next())?;
+                    match value.split_once('=') {
+                        Some((column, limit)) => options
+                            .column_max_chars
+                            .push((column.to_string(), Self::number(&arg, Some(limit.to_string()))?)),
+                        None => options.max_cell_chars = Some(Self::number(&arg, Some(value))?),
+                    }
+                }
+                "--summarize-blobs" => options.summarize_blobs = true,
+                "--where" => options.filter = Some(Self::value(&arg, args.next())?.parse()?),
+                "--dict" => options.dict = true,
+                "--dict-max-distinct" => {
+                    options.dict = true;
+                    options.dict_max_distinct = Some(Self::number(&arg, args.next())?);
+                }
+                "--fold-constants" => options.fold_constants = true,
+                "--profile" => options.profile = Some(ProfileMode::WithRows),
+                "--profile-only" => options.profile = Some(ProfileMode::Only),
+                "--summary" => options.summary = true,
+                "--group-by" => options.group_by.extend(Self::list(&arg, args.next())?),
+                "--parent-columns" => options.parent_columns.extend(Self::list(&arg, args.next())?),
+                "--child-entity" => options.child_entity = Some(Self::value(&arg, args.next())?),
+                "--entity" => options.entities.extend(Self::list(&arg, args.next())?),
+                "--input" => {
+                    // A named input file: `users=users.txt`
+                    let value = Self::value(&arg, args.next())?;
+                    let (name, path) = value.split_once('=').ok_or_else(|| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            format!("Invalid value for --input: {} (expected name=file)", value),
+                        )
+                    })?;
+                    options.inputs.push((name.to_string(), PathBuf::from(path)));
+                }
+                "--multi" => options.multi = true,
+                "--preamble" => options.preamble = Some(Self::value(&arg, args.next())?),
+                "--layout" => options.layout = Some(Self::value(&arg, args.next())?.parse()?),
+                "--vertical-max-rows" => {
+                    options.vertical_max_rows = Some(Self::number(&arg, args.next())?);
+                }
+                "--vertical-min-columns" => {
+                    options.vertical_min_columns = Some(Self::number(&arg, args.next())?);
+                }
+                "--wrap" => options.wrap = Some(Self::value(&arg, args.next())?.parse()?),
+                "--query" => options.query = Some(Self::value(&arg, args.next())?),
+                "--meta" => options.meta = true,
+                "--source" => {
+                    options.meta = true;
+                    options.source = Some(Self::value(&arg, args.next())?);
+                }
+                "--dedupe" => {
+                    options.dedupe.get_or_insert_default();
+                }
+                "--dedupe-order" => {
+                    options.dedupe = Some(Self::value(&arg, args.next())?.parse()?);
+                }
+                "--columns" => {
+                    let selectors = ColumnSelector::parse_list(&Self::value(&arg, args.next())?)?;
+                    options.columns.get_or_insert_with(Vec::new).extend(selectors);
+                }
+                "--exclude" => options
+                    .exclude
+                    .extend(ColumnSelector::parse_list(&Self::value(&arg, args.next())?)?),
+                "--rename" => {
+                    for pair in Self::value(&arg, args.next())?.split(',') {
+                        let (old, new) = pair.split_once('=').ok_or_else(|| {
+                            io::Error::new(
+                                io::ErrorKind::InvalidInput,
+                                format!("Invalid value for --rename: {} (expected old=new)", pair),
+                            )
+                        })?;
+                        options.renames.push((old.trim().to_string(), new.trim().to_string()));
+                    }
+                }
+                "--seed" => options.seed = Some(Self::number(&arg, args.next())? as u64),
+                flag if flag.starts_with("--") => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("Unknown option: {}", flag),
+                    ));
+                }
+                // Positional arguments are accepted and ignored for backward compatibility
+                _ => {}
+            }
+        }
+        if options.group_by.is_empty() && (!options.parent_columns.is_empty() || options.child_entity.is_some()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "--parent-columns and --child-entity require --group-by",
+            ));
+        }
+        let document = options.multi || !options.inputs.is_empty();
+        let conflict = if options.dict && !options.group_by.is_empty() {
+            Some("--dict cannot be combined with --group-by")
+        } else if options.multi && !options.inputs.is_empty() {
+            Some("--multi cannot be combined with --input")
+        } else if !options.inputs.is_empty() && !options.entities.is_empty() {
+            Some("--entity cannot be combined with --input; name each input instead")
+        } else if !document && options.entities.len() > 1 {
+            Some("Several --entity names require --multi")
+        } else if document && options.stats {
+            Some("--stats is not supported for multi-entity documents")
+        } else if !document && options.preamble.is_some() {
+            Some("--preamble requires --multi or --input")
+        } else {
+            None
+        };
+        if let Some(message) = conflict {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+        }
+        Ok(options)
+    }
+
+    fn value(flag: &str, value: Option<String>) -> io::Result<String> {
+        value.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Missing value for {}", flag),
+            )
+        })
+    }
+
+    fn list(flag: &str, value: Option<String>) -> io::Result<Vec<String>> {
+        let value = Self::value(flag, value)?;
+        Ok(value
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(String::from)
+            .collect())
+    }
+
+    fn number(flag: &str, value: Option<String>) -> io::Result<usize> {
+        let value = Self::value(flag, value)?;
+        value.parse().map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid value for {}: {}", flag, value),
+            )
+        })
+    }
+
+    /// Converter configured with the requested limits
+    fn converter(&self, counter: &TokenCounter) -> ToseConverter {
+        let mut converter = ToseConverter::new().with_token_counter(counter.clone());
+        if let (false, [name]) = (self.multi, self.entities.as_slice()) {
+            converter = converter.with_entity_name(name.clone());
+        }
+        if let Some(preamble) = &self.preamble {
+            converter = converter.with_preamble(preamble.clone());
+        }
+        if let Some(filter) = self.filter.clone() {
+            converter = converter.with_filter(filter);
+        }
+        if let Some(max_rows) = self.max_rows {
+            converter = converter.with_max_rows(max_rows);
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            converter = converter.with_max_tokens(max_tokens);
+        }
+        if let Some(max_chars) = self.max_cell_chars {
+            converter = converter.with_max_cell_chars(max_chars);
+        }
+        for (column, max_chars) in &self.column_max_chars {
+            converter = converter.with_column_max_chars(column.clone(), *max_chars);
+        }
+        converter = converter.with_blob_summaries(self.summarize_blobs);
+        if let Some(columns) = self.columns.clone() {
+            converter = converter.with_columns(columns);
+        }
+        converter = converter.with_excluded_columns(self.exclude.clone());
+        for (old, new) in &self.renames {
+            converter = converter.with_rename(old.clone(), new.clone());
+        }
+        if let Some(mode) = self.profile {
+            converter = converter.with_profile(mode);
+        }
+        if let Some(order) = self.dedupe {
+            converter = converter.with_dedupe(order);
+        }
+        if !self.group_by.is_empty() {
+            converter = converter.with_group_by(self.group_by.clone(), self.parent_columns.clone());
+            if let Some(name) = &self.child_entity {
+                converter = converter.with_child_entity(name.clone());
+            }
+        }
+        converter = converter.with_fold_constants(self.fold_constants);
+        if self.dict {
+            converter = converter
+                .with_dictionary_encoding(self.dict_max_distinct.unwrap_or(DEFAULT_DICT_MAX_DISTINCT));
+        }
+        if let Some(template) = self.wrap.clone() {
+            converter = converter.with_wrap(template);
+        }
+        if let Some(query) = &self.query {
+            converter = converter.with_source_query(query.clone());
+        }
+        if self.meta {
+            converter = converter
+                .with_metadata(true)
+                .with_generated_at(SystemTime::now());
+            if let Some(source) = &self.source {
+                converter = converter.with_source(source.clone());
+            }
+        }
+        if let Some(layout) = self.layout {
+            converter = converter.with_layout(layout);
+        }
+        if let Some(max_rows) = self.vertical_max_rows {
+            converter = converter.with_vertical_max_rows(max_rows);
+        }
+        if let Some(min_columns) = self.vertical_min_columns {
+            converter = converter.with_vertical_min_columns(min_columns);
+        }
+        if self.summary {
+            converter = converter.with_summary(
+                self.sample_size.unwrap_or(DEFAULT_SUMMARY_ROWS),
+                self.seed.unwrap_or(DEFAULT_SEED),
+            );
+        }
+        if let Some(method) = self.sample.clone() {
+            let method = match self.seed {
+                Some(seed) => method.with_seed(seed),
+                None => method,
+            };
+            converter = converter.with_sample(method, self.sample_size.unwrap_or(DEFAULT_SAMPLE_SIZE));
+        }
+        converter
+    }
+
+    /// Token counter for `--stats` and `--max-tokens`
+    ///
+    /// Loads the `--vocab` or `TOSE_VOCAB` file when given, else uses chars/4.
+    fn token_counter(&self) -> io::Result<TokenCounter> {
+        if !self.stats && self.max_tokens.is_none() {
+            return Ok(TokenCounter::heuristic());
+        }
+        match self.vocab.clone().or_else(|| env::var_os("TOSE_VOCAB").map(PathBuf::from)) {
+            Some(path) => TokenCounter::from_vocab_file(path),
+            None => Ok(TokenCounter::heuristic()),
+        }
+    }
+}
+
+/// `validate [FILE...]`: check TOSE documents (stdin when no file is given),
+/// printing `FILE:LINE: RULE: MESSAGE` for each violation
+fn validate<I: Iterator<Item = String>>(args: I) -> ExitCode {
+    let mut paths = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--rules" => {
+                for (id, section, summary) in RULES {
+                    println!("{}\t{}\t{}", id, section, summary);
+                }
+                return ExitCode::SUCCESS;
+            }
+            flag if flag.starts_with("--") => {
+                eprintln!("Unknown option: {}", flag);
+                return ExitCode::from(EXIT_UNREADABLE);
+            }
+            _ => paths.push(arg),
+        }
+    }
+
+    let documents: Vec<(String, io::Result<String>)> = if paths.is_empty() {
+        let mut text = String::new();
+        let read = io::stdin().read_to_string(&mut text).map(|_| text);
+        vec![("<stdin>".to_string(), read)]
+    } else {
+        paths
+            .into_iter()
+            .map(|path| {
+                let read = fs::read_to_string(&path);
+                (path, read)
+            })
+            .collect()
+    };
+
+    let mut status = ExitCode::SUCCESS;
+    let mut unreadable = false;
+    for (name, text) in documents {
+        match text {
+            Ok(text) => {
+                let violations = tose_converter::validate(&text);
+                for violation in &violations {
+                    println!("{}:{}", name, violation);
+                }
+                if !violations.is_empty() {
+                    status = ExitCode::from(EXIT_INVALID);
+                }
+            }
+            Err(err) => {
+                eprintln!("{}: {}", name, err);
+                unreadable = true;
+            }
+        }
+    }
+    if unreadable {
+        ExitCode::from(EXIT_UNREADABLE)
+    } else {
+        status
+    }
+}
+
+/// `export --to sql [FILE]`: turn a TOSE document (stdin when no file is
+/// given) into `INSERT` statements, or a `COPY` payload with `--copy`
+fn export<I: Iterator<Item = String>>(mut args: I) -> io::Result<()> {
+    let mut exporter = SqlExporter::new();
+    let mut format = None;
+    let mut path = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--to" => format = Some(Options::value(&arg, args.next())?),
+            "--copy" => exporter = exporter.with_copy(true),
+            "--batch-size" => exporter = exporter.with_batch_size(Options::number(&arg, args.next())?),
+            "--quote-identifiers" => exporter = exporter.with_quoted_identifiers(true),
+            flag if flag.starts_with("--") => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Unknown option: {}", flag),
+                ));
+            }
+            _ => path = Some(PathBuf::from(arg)),
+        }
+    }
+    match format.as_deref() {
+        Some("sql") => {}
+        Some(other) => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Unknown export format: {} (expected sql)", other),
+            ));
+        }
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "export requires --to sql",
+            ));
+        }
+    }
+
+    let stdout = io::stdout();
+    let writer = BufWriter::new(stdout.lock());
+    match path {
+        Some(path) => exporter.export(open(&path)?, writer),
+        None => exporter.export(io::stdin().lock(), writer),
+    }
+}
+
+/// `render [FILE] [--check]`: print a TOSE document (stdin when no file is
+/// given) as psql aligned tables; `--check` first verifies that the tables
+/// parse back into the same rows
+fn render<I: Iterator<Item = String>>(args: I) -> io::Result<()> {
+    let mut check = false;
+    let mut path = None;
+    for arg in args {
+        match arg.as_str() {
+            "--check" => check = true,
+            flag if flag.starts_with("--") => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Unknown option: {}", flag),
+                ));
+            }
+            _ => path = Some(PathBuf::from(arg)),
+        }
+    }
+
+    let mut document = Vec::new();
+    match path {
+        Some(path) => open(&path)?.read_to_end(&mut document)?,
+        None => io::stdin().read_to_end(&mut document)?,
+    };
+    if check {
+        check_psql_round_trip(document.as_slice())?;
+    }
+    let stdout = io::stdout();
+    render_psql(document.as_slice(), BufWriter::new(stdout.lock()))
+}
+
+/// `diff BEFORE AFTER --key COL[,COL...]`: compare two TOSE documents and
+/// print the added, removed and changed rows and the column changes as a
+/// multi-entity document
+fn diff<I: Iterator<Item = String>>(mut args: I) -> io::Result<()> {
+    let mut keys = Vec::new();
+    let mut paths = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--key" => keys.extend(Options::list(&arg, args.next())?),
+            flag if flag.starts_with("--") => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Unknown option: {}", flag),
+                ));
+            }
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    if keys.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "diff requires --key COLUMN",
+        ));
+    }
+    let [before, after] = paths.as_slice() else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "diff requires two files: BEFORE AFTER",
+        ));
+    };
+
+    let stdout = io::stdout();
+    diff_documents(open(before)?, open(after)?, &keys, BufWriter::new(stdout.lock()))
+}
+
+/// Exit status of a subcommand, printing its error as a message
+fn report(result: io::Result<()>) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Open an input file, naming it in the error
+fn open(path: &Path) -> io::Result<File> {
+    File::open(path).map_err(|err| io::Error::new(err.kind(), format!("{}: {}", path.display(), err)))
+}
+
+fn main() -> io::Result<ExitCode> {
+    let mut args = env::args().skip(1).peekable();
+    if args.next_if_eq("validate").is_some() {
+        return Ok(validate(args));
+    }
+    if args.next_if_eq("export").is_some() {
+        return Ok(report(export(args)));
+    }
+    if args.next_if_eq("render").is_some() {
+        return Ok(report(render(args)));
+    }
+    if args.next_if_eq("diff").is_some() {
+        return Ok(report(diff(args)));
+    }
+    let options = Options::parse(args)?;
 
-fn main() -> io::Result<()> {
     // Create converter and process stdin to stdout
-    let converter = ToseConverter::new();
+    let counter = options.token_counter()?;
+    let converter = options.converter(&counter);
     let stdin = io::stdin();
     let stdout = io::stdout();
-    let writer = BufWriter::new(stdout.lock());
+    let mut writer = BufWriter::new(stdout.lock());
 
-    converter.convert(stdin.lock(), writer)?;
+    if !options.inputs.is_empty() {
+        let inputs = options
+            .inputs
+            .iter()
+            .map(|(name, path)| Ok((name.clone(), open(path)?)))
+            .collect::<io::Result<Vec<_>>>()?;
+        converter.convert_document(inputs, writer)?;
+    } else if options.multi {
+        converter.convert_results(stdin.lock(), &options.entities, writer)?;
+    } else if options.stats {
+        let stats = converter.convert_with_stats(stdin.lock(), &mut writer, &counter)?;
+        writer.flush()?;
+        eprint!("{}", stats);
+    } else {
+        converter.convert(stdin.lock(), writer)?;
+    }
 
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
@@ -0,0 +1,271 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// TOSE version written in the `# tose:` metadata line
+pub(crate) const TOSE_VERSION: &str = "1.0";
+
+/// psql status messages that can precede a result without being part of the
+/// echoed query
+const PSQL_NOTICES: [&str; 2] = ["Timing is on.", "Timing is off."];
+
+/// Command tags psql prints for statements that return no rows, e.g. under
+/// `psql -f`; `CREATE ...`, `ALTER ...` and `DROP ...` tags are matched apart
+const COMMAND_TAGS: [&str; 28] = [
+    "ANALYZE",
+    "BEGIN",
+    "CALL",
+    "CHECKPOINT",
+    "CLUSTER",
+    "COMMENT",
+    "COMMIT",
+    "DEALLOCATE",
+    "DECLARE CURSOR",
+    "DISCARD ALL",
+    "DO",
+    "GRANT",
+    "LISTEN",
+    "LOAD",
+    "LOCK TABLE",
+    "NOTIFY",
+    "PREPARE",
+    "REINDEX",
+    "RELEASE",
+    "RESET",
+    "REVOKE",
+    "ROLLBACK",
+    "SAVEPOINT",
+    "SET",
+    "START TRANSACTION",
+    "TRUNCATE TABLE",
+    "UNLISTEN",
+    "VACUUM",
+];
+
+/// Command tags followed by a row count (`INSERT 0 1`, `UPDATE 3`)
+const COUNTED_TAGS: [&str; 8] = [
+    "COPY", "DELETE", "FETCH", "INSERT", "MERGE", "MOVE", "SELECT", "UPDATE",
+];
+
+/// Server messages psql prints ahead of a result when stderr is captured too
+const SERVER_MESSAGES: [&str; 5] = ["NOTICE:", "WARNING:", "INFO:", "DEBUG:", "LOG:"];
+
+/// Output formats tables are parsed from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Dialect {
+    /// psql's default aligned format, with a `(N rows)` footer
+    #[default]
+    PsqlAligned,
+}
+
+/// Where a result came from, as found in the psql output around the table
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceInfo {
+    /// Output format the table was parsed from
+    pub dialect: Dialect,
+    /// Query echoed by `psql -e` before the result
+    pub query: Option<String>,
+    /// Duration reported by psql's `\timing` (`Time: 0.412 ms`)
+    pub time: Option<String>,
+    /// Row count stated by the `(N rows)` footer, if there was one
+    pub footer_count: Option<usize>,
+    /// psql status lines (`Timing is on.`) and server messages (`NOTICE:  ...`)
+    /// printed before the result
+    pub notices: Vec<String>,
+}
+
+/// Format one `# key: value` metadata line (with trailing newline)
+///
+/// Line breaks in the value, with the indentation around them, are folded
+/// into spaces so the line stays a line; other whitespace is kept.
+pub(crate) fn line(key: &str, value: &str) -> String {
+    let value: Vec<&str> = value
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+    format!("# {}: {}\n", key, value.join(" "))
+}
+
+/// Query text from the lines psql printed before the result header, if any
+///
+/// Status lines, server messages and command tags are skipped, and of several
+/// echoed statements only the last, which produced the result, is taken.
+pub(crate) fn echoed_query(lines: &[String]) -> Option<String> {
+    let mut query: Vec<&str> = Vec::new();
+    let mut complete = false;
+    for line in lines.iter().map(|line| line.trim()) {
+        if line.is_empty() || is_notice(line) || is_time_line(line) || is_command_tag(line) {
+            continue;
+        }
+        if complete {
+            query.clear();
+        }
+        query.push(line);
+        complete = line.ends_with(';');
+    }
+    (!query.is_empty()).then(|| query.join(" "))
+}
+
+/// Status lines and server messages among the lines before the result header
+pub(crate) fn notices(lines: &[String]) -> Vec<String> {
+    lines
+        .iter()
+        .map(|line| line.trim())
+        .filter(|line| is_notice(line))
+        .map(String::from)
+        .collect()
+}
+
+/// Whether a (trimmed) line is a psql status line or a server message
+fn is_notice(line: &str) -> bool {
+    PSQL_NOTICES.contains(&line)
+        || SERVER_MESSAGES
+            .iter()
+            .any(|prefix| line.starts_with(prefix))
+}
+
+/// Whether a (trimmed) line is the command tag of a statement without rows
+fn is_command_tag(line: &str) -> bool {
+    if COMMAND_TAGS.contains(&line) {
+        return true;
+    }
+    let mut words = line.split(' ');
+    let first = words.next().unwrap_or_default();
+    let rest: Vec<&str> = words.collect();
+    let all = |valid: fn(u8) -> bool| {
+        !rest.is_empty()
+            && rest
+                .iter()
+                .all(|word| !word.is_empty() && word.bytes().all(valid))
+    };
+    match first {
+        "CREATE" | "ALTER" | "DROP" => all(|b| b.is_ascii_uppercase()),
+        tag if COUNTED_TAGS.contains(&tag) => rest.len() <= 2 && all(|b| b.is_ascii_digit()),
+        _ => false,
+    }
+}
+
+/// Whether a line is psql's `\timing` report
+pub(crate) fn is_time_line(line: &str) -> bool {
+    line.trim_start().starts_with("Time:")
+}
+
+/// Duration from a `Time: 0.412 ms` line
+pub(crate) fn parse_time(line: &str) -> Option<String> {
+    let time = line.trim().strip_prefix("Time:")?.trim();
+    (!time.is_empty()).then(|| time.to_string())
+}
+
+/// Format a point in time as an ISO 8601 UTC timestamp (second precision)
+pub(crate) fn format_timestamp(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let (days, secs_of_day) = (secs / 86_400, secs % 86_400);
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3_600,
+        secs_of_day % 3_600 / 60,
+        secs_of_day % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        let leap_day = UNIX_EPOCH + Duration::from_secs(951_782_400 + 3_661);
+        assert_eq!(format_timestamp(leap_day), "2000-02-29T01:01:01Z");
+    }
+
+    #[test]
+    fn test_echoed_query() {
+        let lines = ["Timing is on.", "SELECT id", "  FROM users;", ""].map(String::from);
+        assert_eq!(
+            echoed_query(&lines),
+            Some("SELECT id FROM users;".to_string())
+        );
+        assert_eq!(echoed_query(&[String::new()]), None);
+    }
+
+    #[test]
+    fn test_command_tags_are_not_the_query() {
+        // psql -f without -e prints only the tags of earlier statements
+        let lines = ["SET", "BEGIN", "INSERT 0 1", "CREATE TEMPORARY TABLE"].map(String::from);
+        assert_eq!(echoed_query(&lines), None);
+
+        // With -e, each statement is echoed before its tag
+        let lines = [
+            "SET search_path = app;",
+            "SET",
+            "SELECT id",
+            "  FROM users;",
+        ]
+        .map(String::from);
+        assert_eq!(
+            echoed_query(&lines),
+            Some("SELECT id FROM users;".to_string())
+        );
+        assert!(!is_command_tag("SELECT id"));
+        assert!(!is_command_tag("UPDATE 1 2 3"));
+    }
+
+    #[test]
+    fn test_notices() {
+        let lines = [
+            "Timing is on.",
+            "NOTICE:  table \"tmp\" does not exist, skipping",
+            "SELECT 1;",
+        ]
+        .map(String::from);
+        assert_eq!(
+            notices(&lines),
+            [
+                "Timing is on.",
+                "NOTICE:  table \"tmp\" does not exist, skipping"
+            ]
+        );
+        assert_eq!(echoed_query(&lines), Some("SELECT 1;".to_string()));
+    }
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(
+            parse_time("Time: 1234.567 ms (00:01.235)"),
+            Some("1234.567 ms (00:01.235)".to_string())
+        );
+        assert_eq!(parse_time("(3 rows)"), None);
+    }
+
+    #[test]
+    fn test_line_folds_newlines() {
+        assert_eq!(
+            line("query", "SELECT 1\n  FROM t"),
+            "# query: SELECT 1 FROM t\n"
+        );
+        assert_eq!(
+            line("query", "SELECT 'a  b'\r\n"),
+            "# query: SELECT 'a  b'\n"
+        );
+    }
+}
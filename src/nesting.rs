@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+use std::io;
+
+/// Indent of child blocks under their parent record
+pub(crate) const INDENT: &str = "  ";
+
+/// How to nest a denormalized join result under its parent columns
+#[derive(Debug, Clone)]
+pub(crate) struct Nesting {
+    /// Columns identifying a parent
+    pub(crate) keys: Vec<String>,
+    /// Further columns that belong to the parent rather than the children
+    pub(crate) parents: Vec<String>,
+    /// Entity name of the child blocks
+    pub(crate) child_entity: String,
+}
+
+/// Child rows of each parent record, in parent order
+#[derive(Debug, Clone)]
+pub(crate) struct Children {
+    pub(crate) entity: String,
+    pub(crate) columns: Vec<String>,
+    pub(crate) blocks: Vec<Vec<Vec<String>>>,
+}
+
+impl Children {
+    /// Format the indented child block of parent `idx` (with trailing newline)
+    pub(crate) fn block(&self, idx: usize, csv_row: impl Fn(&[String]) -> String) -> String {
+        let rows = &self.blocks[idx];
+        let mut block = format!(
+            "{}{}[{}]{{{}}}:\n",
+            INDENT,
+            self.entity,
+            rows.len(),
+            self.columns.join(",")
+        );
+        for row in rows {
+            // Continuation lines of multi-line fields are indented too
+            block.push_str(INDENT);
+            block.push_str(&csv_row(row).replace('\n', &format!("\n{}", INDENT)));
+            block.push('\n');
+        }
+        block
+    }
+}
+
+impl Nesting {
+    /// Group rows by the key columns, leaving one row of parent columns per
+    /// group and returning the remaining columns as child rows
+    ///
+    /// Groups keep the order in which their key first appears. A group whose
+    /// only child row is entirely NULL (an outer join miss) gets no children.
+    pub(crate) fn apply(
+        &self,
+        columns: &mut Vec<String>,
+        rows: &mut Vec<Vec<String>>,
+    ) -> io::Result<Children> {
+        let position = |name: &String| {
+            columns.iter().position(|c| c == name).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Unknown column for --group-by: {}", name),
+                )
+            })
+        };
+        let keys = self
+            .keys
+            .iter()
+            .map(position)
+            .collect::<io::Result<Vec<_>>>()?;
+        let mut parent_cols = keys.clone();
+        for idx in self.parents.iter().map(position) {
+            let idx = idx?;
+            if !parent_cols.contains(&idx) {
+                parent_cols.push(idx);
+            }
+        }
+        parent_cols.sort_unstable();
+        let child_cols: Vec<usize> = (0..columns.len())
+            .filter(|idx| !parent_cols.contains(idx))
+            .collect();
+        if child_cols.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "--group-by leaves no child columns",
+            ));
+        }
+
+        let pick = |row: &[String], cols: &[usize]| -> Vec<String> {
+            cols.iter().map(|&idx| row[idx].clone()).collect()
+        };
+
+        let mut parents: Vec<Vec<String>> = Vec::new();
+        let mut blocks: Vec<Vec<Vec<String>>> = Vec::new();
+        let mut index: HashMap<Vec<String>, usize> = HashMap::new();
+        for row in rows.iter() {
+            let key = pick(row, &keys);
+            let parent = pick(row, &parent_cols);
+            let group = match index.get(&key) {
+                Some(&group) => {
+                    if parents[group] != parent {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            format!(
+                                "Parent columns differ within group {}",
+                                describe_key(&self.keys, &key)
+                            ),
+                        ));
+                    }
+                    group
+                }
+                None => {
+                    index.insert(key, parents.len());
+                    parents.push(parent);
+                    blocks.push(Vec::new());
+                    parents.len() - 1
+                }
+            };
+            blocks[group].push(pick(row, &child_cols));
+        }
+
+        for block in &mut blocks {
+            if block.len() == 1 && block[0].iter().all(String::is_empty) {
+                block.clear();
+            }
+        }
+
+        let children = Children {
+            entity: self.child_entity.clone(),
+            columns: pick(columns, &child_cols),
+            blocks,
+        };
+        *columns = pick(columns, &parent_cols);
+        *rows = parents;
+        Ok(children)
+    }
+}
+
+/// Render a group key as `col=value` pairs for error messages
+fn describe_key(columns: &[String], values: &[String]) -> String {
+    columns
+        .iter()
+        .zip(values)
+        .map(|(column, value)| format!("{}={}", column, value))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    fn nesting(parents: &[&str]) -> Nesting {
+        Nesting {
+            keys: strings(&["order_id"]),
+            parents: strings(parents),
+            child_entity: "items".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_groups_children_under_parents() {
+        let mut columns = strings(&["order_id", "customer", "sku", "qty"]);
+        let mut rows = vec![
+            strings(&["1", "Alice", "A1", "2"]),
+            strings(&["2", "Bob", "", ""]),
+            strings(&["1", "Alice", "B7", "1"]),
+        ];
+
+        let children = nesting(&["customer"])
+            .apply(&mut columns, &mut rows)
+            .unwrap();
+
+        assert_eq!(columns, strings(&["order_id", "customer"]));
+        assert_eq!(rows, vec![strings(&["1", "Alice"]), strings(&["2", "Bob"])]);
+        assert_eq!(children.columns, strings(&["sku", "qty"]));
+        assert_eq!(children.blocks[0].len(), 2);
+        assert!(children.blocks[1].is_empty());
+        assert_eq!(
+            children.block(0, |row| row.join(",")),
+            "  items[2]{sku,qty}:\n  A1,2\n  B7,1\n"
+        );
+    }
+
+    #[test]
+    fn test_multiline_fields_stay_indented() {
+        let children = Children {
+            entity: "notes".to_string(),
+            columns: strings(&["body"]),
+            blocks: vec![vec![strings(&["\"line 1\nline 2\""])]],
+        };
+        assert_eq!(
+            children.block(0, |row| row.join(",")),
+            "  notes[1]{body}:\n  \"line 1\n  line 2\"\n"
+        );
+    }
+
+    #[test]
+    fn test_inconsistent_parent_rejected() {
+        let mut columns = strings(&["order_id", "customer", "sku"]);
+        let mut rows = vec![
+            strings(&["1", "Alice", "A1"]),
+            strings(&["1", "Alicia", "B7"]),
+        ];
+        let err = nesting(&["customer"])
+            .apply(&mut columns, &mut rows)
+            .unwrap_err();
+        assert!(err.to_string().contains("group order_id=1"));
+    }
+
+    #[test]
+    fn test_unknown_or_exhaustive_columns_rejected() {
+        let mut columns = strings(&["order_id", "sku"]);
+        let mut rows = Vec::new();
+        assert!(
+            nesting(&["missing"])
+                .apply(&mut columns, &mut rows)
+                .is_err()
+        );
+        assert!(nesting(&["sku"]).apply(&mut columns, &mut rows).is_err());
+    }
+}
@@ -0,0 +1,371 @@
+//! Small, dependency-free text matchers: wildcards (glob and SQL `LIKE`)
+//! and a backtracking regular expression subset.
+
+use std::io;
+
+/// Match `text` against a wildcard pattern where `many` matches any run of
+/// characters and `one` matches exactly one character
+pub(crate) fn wildcard_match(pattern: &str, text: &str, many: char, one: char) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    // Iterative matcher with single-star backtracking
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == one || pattern[p] == text[t]) && pattern[p] != many {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == many {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == many)
+}
+
+/// A compiled regular expression
+///
+/// Supports literals, `.`, `[...]` classes (with ranges and `^` negation),
+/// `\d \w \s` and their negations, `^`/`$` anchors, `* + ?` quantifiers,
+/// groups and `|` alternation. Matching is unanchored unless `^`/`$` are used.
+#[derive(Debug, Clone)]
+pub(crate) struct Regex {
+    root: Node,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Char(char),
+    Any,
+    Class {
+        items: Vec<ClassItem>,
+        negated: bool,
+    },
+    Start,
+    End,
+    Concat(Vec<Node>),
+    Alt(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+    },
+}
+
+#[derive(Debug, Clone)]
+enum ClassItem {
+    Range(char, char),
+    Digit(bool),
+    Word(bool),
+    Space(bool),
+}
+
+impl ClassItem {
+    fn matches(&self, c: char) -> bool {
+        match *self {
+            ClassItem::Range(lo, hi) => lo <= c && c <= hi,
+            ClassItem::Digit(positive) => c.is_ascii_digit() == positive,
+            ClassItem::Word(positive) => (c.is_alphanumeric() || c == '_') == positive,
+            ClassItem::Space(positive) => c.is_whitespace() == positive,
+        }
+    }
+}
+
+impl Regex {
+    /// Compile a pattern, reporting the position of any syntax error
+    pub(crate) fn new(pattern: &str) -> io::Result<Self> {
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut parser = Parser {
+            chars: &chars,
+            pos: 0,
+        };
+        let root = parser.alternation()?;
+        if parser.pos < chars.len() {
+            return Err(parser.error("unmatched ')'"));
+        }
+        Ok(Regex { root })
+    }
+
+    /// Whether the pattern matches anywhere in `text`
+    pub(crate) fn is_match(&self, text: &str) -> bool {
+        let chars: Vec<char> = text.chars().collect();
+        (0..=chars.len()).any(|start| match_node(&self.root, &chars, start, &mut |_| true))
+    }
+}
+
+/// Match `node` at `pos`, calling `next` with each possible end position
+fn match_node(node: &Node, text: &[char], pos: usize, next: &mut dyn FnMut(usize) -> bool) -> bool {
+    match node {
+        Node::Char(c) => text.get(pos) == Some(c) && next(pos + 1),
+        Node::Any => pos < text.len() && next(pos + 1),
+        Node::Class { items, negated } => match text.get(pos) {
+            Some(&c) => items.iter().any(|item| item.matches(c)) != *negated && next(pos + 1),
+            None => false,
+        },
+        Node::Start => pos == 0 && next(pos),
+        Node::End => pos == text.len() && next(pos),
+        Node::Concat(nodes) => match_sequence(nodes, text, pos, next),
+        Node::Alt(options) => options
+            .iter()
+            .any(|option| match_node(option, text, pos, next)),
+        Node::Repeat { node, min, max } => match_repeat(node, *min, *max, 0, text, pos, next),
+    }
+}
+
+fn match_sequence(
+    nodes: &[Node],
+    text: &[char],
+    pos: usize,
+    next: &mut dyn FnMut(usize) -> bool,
+) -> bool {
+    match nodes.split_first() {
+        None => next(pos),
+        Some((first, rest)) => match_node(first, text, pos, &mut |end| {
+            match_sequence(rest, text, end, next)
+        }),
+    }
+}
+
+/// Greedy repetition: try one more occurrence before settling for fewer
+fn match_repeat(
+    node: &Node,
+    min: usize,
+    max: Option<usize>,
+    count: usize,
+    text: &[char],
+    pos: usize,
+    next: &mut dyn FnMut(usize) -> bool,
+) -> bool {
+    if max.is_none_or(|max| count < max)
+        && match_node(node, text, pos, &mut |end| {
+            // Stop zero-width loops from recursing forever
+            end != pos && match_repeat(node, min, max, count + 1, text, end, next)
+        })
+    {
+        return true;
+    }
+    count >= min && next(pos)
+}
+
+struct Parser<'a> {
+    chars: &'a [char],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid regex at position {}: {}", self.pos, message),
+        )
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn alternation(&mut self) -> io::Result<Node> {
+        let mut options = vec![self.concat()?];
+        while self.peek() == Some('|') {
+            self.pos += 1;
+            options.push(self.concat()?);
+        }
+        Ok(if options.len() == 1 {
+            options.remove(0)
+        } else {
+            Node::Alt(options)
+        })
+    }
+
+    fn concat(&mut self) -> io::Result<Node> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.atom()?;
+            nodes.push(self.quantifier(atom)?);
+        }
+        Ok(Node::Concat(nodes))
+    }
+
+    fn quantifier(&mut self, atom: Node) -> io::Result<Node> {
+        let (min, max) = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            _ => return Ok(atom),
+        };
+        if matches!(atom, Node::Start | Node::End) {
+            return Err(self.error("nothing to repeat"));
+        }
+        self.pos += 1;
+        Ok(Node::Repeat {
+            node: Box::new(atom),
+            min,
+            max,
+        })
+    }
+
+    fn atom(&mut self) -> io::Result<Node> {
+        let c = self.peek().ok_or_else(|| self.error("unexpected end"))?;
+        self.pos += 1;
+        match c {
+            '.' => Ok(Node::Any),
+            '^' => Ok(Node::Start),
+            '$' => Ok(Node::End),
+            '*' | '+' | '?' => {
+                self.pos -= 1;
+                Err(self.error("nothing to repeat"))
+            }
+            '(' => {
+                let inner = self.alternation()?;
+                if self.peek() != Some(')') {
+                    return Err(self.error("missing ')'"));
+                }
+                self.pos += 1;
+                Ok(inner)
+            }
+            '[' => self.class(),
+            '\\' => match self.escape()? {
+                Ok(c) => Ok(Node::Char(c)),
+                Err(item) => Ok(Node::Class {
+                    items: vec![item],
+                    negated: false,
+                }),
+            },
+            c => Ok(Node::Char(c)),
+        }
+    }
+
+    /// Parse the character after a backslash: a literal or a shorthand class
+    fn escape(&mut self) -> io::Result<Result<char, ClassItem>> {
+        let c = self
+            .peek()
+            .ok_or_else(|| self.error("trailing backslash"))?;
+        self.pos += 1;
+        Ok(match c {
+            'd' => Err(ClassItem::Digit(true)),
+            'D' => Err(ClassItem::Digit(false)),
+            'w' => Err(ClassItem::Word(true)),
+            'W' => Err(ClassItem::Word(false)),
+            's' => Err(ClassItem::Space(true)),
+            'S' => Err(ClassItem::Space(false)),
+            'n' => Ok('\n'),
+            't' => Ok('\t'),
+            c => Ok(c),
+        })
+    }
+
+    fn class(&mut self) -> io::Result<Node> {
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.pos += 1;
+        }
+
+        let mut items = Vec::new();
+        let mut first = true;
+        loop {
+            let c = self.peek().ok_or_else(|| self.error("missing ']'"))?;
+            self.pos += 1;
+            if c == ']' && !first {
+                break;
+            }
+            first = false;
+
+            let lo = match c {
+                '\\' => match self.escape()? {
+                    Ok(c) => c,
+                    Err(item) => {
+                        items.push(item);
+                        continue;
+                    }
+                },
+                c => c,
+            };
+
+            if self.peek() == Some('-') && self.chars.get(self.pos + 1).is_some_and(|&c| c != ']') {
+                let hi = self.chars[self.pos + 1];
+                self.pos += 2;
+                if hi < lo {
+                    return Err(self.error("invalid class range"));
+                }
+                items.push(ClassItem::Range(lo, hi));
+            } else {
+                items.push(ClassItem::Range(lo, lo));
+            }
+        }
+
+        Ok(Node::Class { items, negated })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob() {
+        assert!(wildcard_match("created_*", "created_at", '*', '?'));
+        assert!(wildcard_match("*_id", "user_id", '*', '?'));
+        assert!(wildcard_match("c?l", "col", '*', '?'));
+        assert!(!wildcard_match("c?l", "cool", '*', '?'));
+        assert!(wildcard_match("*a*b*", "xaxxbx", '*', '?'));
+        assert!(!wildcard_match("*a*b", "xaxxbx", '*', '?'));
+    }
+
+    #[test]
+    fn test_like() {
+        assert!(wildcard_match("%@example.com", "bob@example.com", '%', '_'));
+        assert!(wildcard_match("a_c", "abc", '%', '_'));
+        assert!(!wildcard_match("a_c", "ac", '%', '_'));
+    }
+
+    #[test]
+    fn test_regex_basics() {
+        let re = Regex::new("^(created|updated)_at$").unwrap();
+        assert!(re.is_match("created_at"));
+        assert!(re.is_match("updated_at"));
+        assert!(!re.is_match("deleted_at"));
+
+        let re = Regex::new(r"_id$").unwrap();
+        assert!(re.is_match("user_id"));
+        assert!(!re.is_match("identity"));
+
+        let re = Regex::new(r"^[a-c]\d+x?$").unwrap();
+        assert!(re.is_match("b12"));
+        assert!(re.is_match("a1x"));
+        assert!(!re.is_match("d1"));
+
+        let re = Regex::new("[^0-9]").unwrap();
+        assert!(re.is_match("12a"));
+        assert!(!re.is_match("123"));
+    }
+
+    #[test]
+    fn test_regex_nested_repeat_terminates() {
+        let re = Regex::new("^(a*)*$").unwrap();
+        assert!(re.is_match("aaa"));
+        assert!(!re.is_match("aab"));
+    }
+
+    #[test]
+    fn test_regex_errors() {
+        assert!(
+            Regex::new("(abc")
+                .unwrap_err()
+                .to_string()
+                .contains("missing ')'")
+        );
+        assert!(Regex::new("abc)").is_err());
+        assert!(Regex::new("*a").is_err());
+        assert!(Regex::new("[z-a]").is_err());
+    }
+}
@@ -0,0 +1,307 @@
+use std::collections::HashMap;
+
+use crate::filter::decimal;
+
+/// Fields of the statistics entity, one record per profiled column
+pub(crate) const FIELDS: [&str; 9] = [
+    "column", "type", "nulls", "distinct", "min", "max", "mean", "top", "length",
+];
+
+/// Most frequent values listed in the `top` field
+const TOP_VALUES: usize = 3;
+
+/// Whether `--profile` statistics accompany or replace the data rows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfileMode {
+    /// Write the rows, followed by the statistics entity
+    WithRows,
+    /// Write only the statistics entity
+    Only,
+}
+
+/// Most distinct values tracked per column before `distinct` becomes a lower bound
+const MAX_TRACKED_VALUES: usize = 10_000;
+
+/// Running statistics for one column, fed one value at a time
+#[derive(Debug, Default)]
+pub(crate) struct ColumnStats {
+    nulls: usize,
+    values: usize,
+    /// Cleared as soon as a value does not parse as an integer
+    not_integer: bool,
+    /// Set as soon as a value is not a finite decimal literal
+    not_number: bool,
+    /// Running mean, which unlike a sum cannot overflow
+    mean: f64,
+    min_number: Option<(f64, String)>,
+    max_number: Option<(f64, String)>,
+    min_text: Option<String>,
+    max_text: Option<String>,
+    min_len: usize,
+    max_len: usize,
+    /// Value -> (first-seen position, occurrences)
+    counts: HashMap<String, (usize, usize)>,
+    overflowed: bool,
+}
+
+impl ColumnStats {
+    /// Record one cell (empty means NULL)
+    pub(crate) fn add(&mut self, value: &str) {
+        if value.is_empty() {
+            self.nulls += 1;
+            return;
+        }
+
+        let len = value.chars().count();
+        if self.values == 0 {
+            self.min_len = len;
+        }
+        self.min_len = self.min_len.min(len);
+        self.max_len = self.max_len.max(len);
+        self.values += 1;
+
+        if !self.not_integer && value.parse::<i64>().is_err() {
+            self.not_integer = true;
+        }
+        if !self.not_number {
+            match decimal(value) {
+                Some(number) => {
+                    let n = self.values as f64;
+                    self.mean += number / n - self.mean / n;
+                    if self
+                        .min_number
+                        .as_ref()
+                        .is_none_or(|(min, _)| number < *min)
+                    {
+                        self.min_number = Some((number, value.to_string()));
+                    }
+                    if self
+                        .max_number
+                        .as_ref()
+                        .is_none_or(|(max, _)| number > *max)
+                    {
+                        self.max_number = Some((number, value.to_string()));
+                    }
+                }
+                None => self.not_number = true,
+            }
+        }
+        if self.min_text.as_deref().is_none_or(|min| value < min) {
+            self.min_text = Some(value.to_string());
+        }
+        if self.max_text.as_deref().is_none_or(|max| value > max) {
+            self.max_text = Some(value.to_string());
+        }
+
+        let seen = self.counts.len();
+        match self.counts.get_mut(value) {
+            Some((_, count)) => *count += 1,
+            None if seen < MAX_TRACKED_VALUES => {
+                self.counts.insert(value.to_string(), (seen, 1));
+            }
+            None => self.overflowed = true,
+        }
+    }
+
+    /// Format the statistics as a record laid out as [`FIELDS`]
+    pub(crate) fn record(&self, column: &str) -> Vec<String> {
+        let numeric = self.values > 0 && !self.not_number;
+        let kind = if self.values == 0 {
+            "null"
+        } else if !self.not_integer {
+            "integer"
+        } else if numeric {
+            "number"
+        } else {
+            "text"
+        };
+
+        let (min, max, mean) = if numeric {
+            (
+                self.min_number.as_ref().map(|(_, v)| v.clone()),
+                self.max_number.as_ref().map(|(_, v)| v.clone()),
+                Some(format_number(self.mean)),
+            )
+        } else {
+            (self.min_text.clone(), self.max_text.clone(), None)
+        };
+
+        let distinct = if self.overflowed {
+            format!("{}+", MAX_TRACKED_VALUES)
+        } else {
+            self.counts.len().to_string()
+        };
+
+        // Top values are only exact while every distinct value is tracked
+        let top = if self.overflowed {
+            String::new()
+        } else {
+            let mut counts: Vec<(&String, &(usize, usize))> = self.counts.iter().collect();
+            counts.sort_by_key(|&(_, &(first, count))| (std::cmp::Reverse(count), first));
+            counts
+                .iter()
+                .take_while(|(_, (_, count))| *count > 1)
+                .take(TOP_VALUES)
+                .map(|(value, (_, count))| format!("{}={}", top_value(value), count))
+                .collect::<Vec<_>>()
+                .join(";")
+        };
+
+        let length = if self.values > 0 {
+            format!("{}..{}", self.min_len, self.max_len)
+        } else {
+            String::new()
+        };
+
+        vec![
+            column.to_string(),
+            kind.to_string(),
+            self.nulls.to_string(),
+            distinct,
+            min.unwrap_or_default(),
+            max.unwrap_or_default(),
+            mean.unwrap_or_default(),
+            top,
+            length,
+        ]
+    }
+}
+
+/// Compute one statistics record per column, laid out as [`FIELDS`]
+///
+/// Numeric columns compare and average by value, text columns compare
+/// lexicographically. `top` lists up to three `value=count` pairs of values
+/// occurring more than once (quoted when they contain `=`, `;` or `"`), `length` is the `MIN..MAX` character length.
+/// Fields that do not apply are left NULL.
+pub(crate) fn profile(columns: &[String], rows: &[Vec<String>]) -> Vec<Vec<String>> {
+    let mut stats: Vec<ColumnStats> = columns.iter().map(|_| ColumnStats::default()).collect();
+    for row in rows {
+        for (column, value) in stats.iter_mut().zip(row) {
+            column.add(value);
+        }
+    }
+    columns
+        .iter()
+        .zip(&stats)
+        .map(|(name, column)| column.record(name))
+        .collect()
+}
+
+/// Quote a top value containing `=`, `;` or `"`, doubling its quotes
+fn top_value(value: &str) -> String {
+    if value.contains(['=', ';', '"']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Format a mean with at most two decimals and no trailing zeros
+fn format_number(value: f64) -> String {
+    let rounded = format!("{:.2}", value);
+    rounded
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_profile_columns() {
+        let columns = strings(&["id", "status", "total", "note"]);
+        let rows = vec![
+            strings(&["1", "shipped", "9.99", ""]),
+            strings(&["2", "pending", "14.5", ""]),
+            strings(&["10", "shipped", "3", ""]),
+        ];
+
+        let stats = profile(&columns, &rows);
+
+        assert_eq!(
+            stats[0],
+            strings(&["id", "integer", "0", "3", "1", "10", "4.33", "", "1..2"])
+        );
+        assert_eq!(
+            stats[1],
+            strings(&[
+                "status",
+                "text",
+                "0",
+                "2",
+                "pending",
+                "shipped",
+                "",
+                "shipped=2",
+                "7..7"
+            ])
+        );
+        assert_eq!(
+            stats[2][1..7],
+            strings(&["number", "0", "3", "3", "14.5", "9.16"])
+        );
+        assert_eq!(
+            stats[3],
+            strings(&["note", "null", "3", "0", "", "", "", "", ""])
+        );
+    }
+
+    #[test]
+    fn test_format_number() {
+        assert_eq!(format_number(2.0), "2");
+        assert_eq!(format_number(2.5), "2.5");
+        assert_eq!(format_number(1.0 / 3.0), "0.33");
+    }
+
+    #[test]
+    fn test_distinct_becomes_lower_bound() {
+        let mut stats = ColumnStats::default();
+        for i in 0..=MAX_TRACKED_VALUES {
+            stats.add(&format!("v{}", i));
+        }
+        let record = stats.record("c");
+        assert_eq!(record[3], format!("{}+", MAX_TRACKED_VALUES));
+        assert_eq!(record[7], "");
+    }
+
+    #[test]
+    fn test_only_finite_decimals_are_numbers() {
+        let columns = strings(&["a", "b", "c"]);
+        let rows = vec![
+            strings(&["1.5", "NaN", "1e308"]),
+            strings(&["2.5", "2", "1e308"]),
+            strings(&["inf", "3", "1e308"]),
+        ];
+
+        let stats = profile(&columns, &rows);
+
+        assert_eq!(
+            stats[0][1..7],
+            strings(&["text", "0", "3", "1.5", "inf", ""])
+        );
+        assert_eq!(stats[1][1], "text");
+        assert_eq!(
+            stats[2][1..6],
+            strings(&["number", "0", "1", "1e308", "1e308"])
+        );
+        assert_eq!(stats[2][6].parse::<f64>().unwrap(), 1e308);
+    }
+
+    #[test]
+    fn test_top_values_quote_separators() {
+        let mut stats = ColumnStats::default();
+        for value in ["a=b", "a=b", "x;y", "x;y", "say \"hi\"", "say \"hi\""] {
+            stats.add(value);
+        }
+        assert_eq!(
+            stats.record("c")[7],
+            "\"a=b\"=2;\"x;y\"=2;\"say \"\"hi\"\"\"=2"
+        );
+    }
+}
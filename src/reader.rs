@@ -0,0 +1,904 @@
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read};
+
+use crate::dictionary::Legend;
+use crate::nesting::INDENT;
+
+/// A data row: one cell per field, `None` for NULL
+pub type Row = Vec<Option<String>>;
+
+/// A malformed TOSE document, with the 1-based line and column of the problem
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+impl Error for ParseError {}
+
+impl From<ParseError> for io::Error {
+    fn from(err: ParseError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+/// Build an `InvalidData` error carrying a [`ParseError`]
+fn invalid(line: usize, column: usize, message: impl Into<String>) -> io::Error {
+    ParseError {
+        line,
+        column,
+        message: message.into(),
+    }
+    .into()
+}
+
+/// Column (1-based, in characters) of a byte offset in a line
+fn column_of(line: &str, offset: usize) -> usize {
+    line[..offset.min(line.len())].chars().count() + 1
+}
+
+/// Byte offset and message of a problem within one line
+type SyntaxError = (usize, String);
+
+/// A parsed schema header
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Header {
+    pub entity: String,
+    /// Field list as written; for the vertical layout `field,value_1,...`
+    pub fields: Vec<String>,
+    /// Records in the data block
+    pub shown: usize,
+    /// Rows in the original result (equal to `shown` unless partial)
+    pub total: usize,
+    pub sample: Option<String>,
+    pub deduped_from: Option<usize>,
+    /// Child entity written under each record
+    pub nested: Option<String>,
+    pub vertical: bool,
+    /// Dictionary-encoded columns, in legend order
+    pub dictionary: Vec<String>,
+    /// Folded constant columns, `None` for a column that is NULL throughout
+    pub constants: Vec<(String, Option<String>)>,
+}
+
+impl Header {
+    /// Whether the data block holds only part of the result
+    pub fn is_partial(&self) -> bool {
+        self.shown < self.total
+    }
+
+    /// Parse a schema header line, reporting the byte offset of any error
+    pub(crate) fn parse(line: &str) -> Result<Self, SyntaxError> {
+        let open = line
+            .find('[')
+            .ok_or_else(|| (line.len(), "expected '[' after the entity name".to_string()))?;
+        check_name(&line[..open], 0)?;
+        Self::parse_after_name(line, open)
+    }
+
+    /// Parse a schema header whose entity name (before `[` at `open`) is
+    /// taken as written
+    pub(crate) fn parse_after_name(line: &str, open: usize) -> Result<Self, SyntaxError> {
+        let entity = &line[..open];
+        let close = line[open..]
+            .find(']')
+            .map(|i| open + i)
+            .ok_or_else(|| (line.len(), "expected ']' after the row count".to_string()))?;
+        let mut header = Header {
+            entity: entity.to_string(),
+            ..Header::default()
+        };
+        header.parse_count(&line[open + 1..close], open + 1)?;
+
+        let fields_start = close + 1;
+        if !line[fields_start..].starts_with('{') {
+            return Err((fields_start, "expected '{' after the row count".to_string()));
+        }
+        let fields_end = line[fields_start..]
+            .find('}')
+            .map(|i| fields_start + i)
+            .ok_or_else(|| (line.len(), "expected '}' after the field list".to_string()))?;
+        let fields = &line[fields_start + 1..fields_end];
+        if fields.is_empty() {
+            return Err((fields_end, "empty field list".to_string()));
+        }
+        header.fields = fields.split(',').map(String::from).collect();
+
+        let mut end = fields_end + 1;
+        if line[end..].starts_with('|') {
+            let terminator = line.len() - usize::from(line.ends_with(':'));
+            header.constants = parse_constants(&line[end + 1..terminator], end + 1)?;
+            end = terminator;
+        }
+        if &line[end..] != ":" {
+            return Err((end, "expected ':' at the end of the header".to_string()));
+        }
+        Ok(header)
+    }
+
+    /// Parse the row count and its qualifiers: `3`, `2 of 3, sample=head`, ...
+    fn parse_count(&mut self, text: &str, base: usize) -> Result<(), SyntaxError> {
+        let mut parts = text.split(", ");
+        let count = parts.next().unwrap_or_default();
+        match count.split_once(" of ") {
+            Some((shown, total)) => {
+                self.shown = parse_number(shown, base)?;
+                self.total = parse_number(total, base + shown.len() + " of ".len())?;
+                if self.shown > self.total {
+                    return Err((base, "shown rows exceed the total".to_string()));
+                }
+            }
+            None => {
+                self.shown = parse_number(count, base)?;
+                self.total = self.shown;
+            }
+        }
+
+        let mut pos = base + count.len();
+        for qualifier in parts {
+            pos += ", ".len();
+            if let Some(method) = qualifier.strip_prefix("sample=") {
+                self.sample = Some(method.to_string());
+            } else if let Some(original) = qualifier.strip_prefix("deduped from ") {
+                self.deduped_from = Some(parse_number(original, pos + "deduped from ".len())?);
+            } else if let Some(entity) = qualifier.strip_prefix("nested=") {
+                check_name(entity, pos + "nested=".len())?;
+                self.nested = Some(entity.to_string());
+            } else if qualifier == "vertical" {
+                self.vertical = true;
+            } else if let Some(columns) = qualifier.strip_prefix("dict=") {
+                self.dictionary = columns.split('+').map(String::from).collect();
+            } else {
+                return Err((pos, format!("unknown qualifier {:?}", qualifier)));
+            }
+            pos += qualifier.len();
+        }
+        Ok(())
+    }
+}
+
+/// Check an entity name (`[A-Za-z0-9_]+`) starting at byte `base`
+pub(crate) fn check_name(name: &str, base: usize) -> Result<(), SyntaxError> {
+    if name.is_empty() {
+        return Err((base, "missing entity name".to_string()));
+    }
+    match name
+        .char_indices()
+        .find(|&(_, c)| !c.is_ascii_alphanumeric() && c != '_')
+    {
+        Some((pos, c)) => Err((
+            base + pos,
+            format!("invalid character {:?} in entity name", c),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Parse a row count made of digits only
+fn parse_number(text: &str, pos: usize) -> Result<usize, SyntaxError> {
+    text.bytes()
+        .all(|b| b.is_ascii_digit())
+        .then(|| text.parse().ok())
+        .flatten()
+        .ok_or_else(|| (pos, format!("expected a row count, found {:?}", text)))
+}
+
+/// Parse `col=value,col=NULL,col="quoted"` constant annotations
+fn parse_constants(text: &str, base: usize) -> Result<Vec<(String, Option<String>)>, SyntaxError> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let at = |i: usize| chars.get(i).map(|&(pos, c)| (base + pos, c));
+    let end = base + text.len();
+    let mut constants = Vec::new();
+    let mut i = 0;
+
+    loop {
+        let start = i;
+        while at(i).is_some_and(|(_, c)| c != '=') {
+            i += 1;
+        }
+        let column: String = chars[start..i].iter().map(|&(_, c)| c).collect();
+        if column.is_empty() || at(i).is_none() {
+            let pos = at(start).map_or(end, |(pos, _)| pos);
+            return Err((pos, "expected COLUMN=VALUE".to_string()));
+        }
+        i += 1;
+
+        let value = if at(i).map(|(_, c)| c) == Some('"') {
+            let open = at(i).map_or(end, |(pos, _)| pos);
+            let mut value = String::new();
+            i += 1;
+            loop {
+                match at(i).map(|(_, c)| c) {
+                    None => return Err((open, "unterminated quoted value".to_string())),
+                    Some('"') if at(i + 1).map(|(_, c)| c) == Some('"') => {
+                        value.push('"');
+                        i += 2;
+                    }
+                    Some('"') => {
+                        i += 1;
+                        break;
+                    }
+                    Some(c) => {
+                        value.push(c);
+                        i += 1;
+                    }
+                }
+            }
+            Some(value)
+        } else {
+            let start = i;
+            while at(i).is_some_and(|(_, c)| c != ',') {
+                i += 1;
+            }
+            let value: String = chars[start..i].iter().map(|&(_, c)| c).collect();
+            (value != "NULL").then_some(value)
+        };
+        constants.push((column, value));
+
+        match at(i) {
+            None => return Ok(constants),
+            Some((_, ',')) => i += 1,
+            Some((pos, _)) => return Err((pos, "expected ',' after a constant".to_string())),
+        }
+    }
+}
+
+/// Input lines with one line of lookahead, counted from 1
+struct Lines<R> {
+    input: R,
+    /// Number of the last line returned by `next`
+    number: usize,
+    peeked: Option<Option<String>>,
+}
+
+impl<R: BufRead> Lines<R> {
+    fn read(&mut self) -> io::Result<Option<String>> {
+        let mut line = String::new();
+        if self.input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        if line.ends_with('\n') {
+            line.pop();
+        }
+        Ok(Some(line))
+    }
+
+    fn next(&mut self) -> io::Result<Option<String>> {
+        let line = match self.peeked.take() {
+            Some(line) => line,
+            None => self.read()?,
+        };
+        if line.is_some() {
+            self.number += 1;
+        }
+        Ok(line)
+    }
+
+    fn peek(&mut self) -> io::Result<Option<&str>> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.read()?);
+        }
+        Ok(self.peeked.as_ref().and_then(|line| line.as_deref()))
+    }
+}
+
+/// One record of a data block, with the position of each field
+pub(crate) struct Record {
+    pub(crate) fields: Row,
+    /// (line, column) where each field starts
+    pub(crate) positions: Vec<(usize, usize)>,
+    /// Position just past the last character of the record
+    pub(crate) end: (usize, usize),
+}
+
+/// Whether the lines read so far end inside a quoted field
+pub(crate) fn continues(lines: &[String]) -> bool {
+    lines
+        .iter()
+        .map(|line| line.matches('"').count())
+        .sum::<usize>()
+        % 2
+        == 1
+}
+
+/// Split the physical lines of one RFC 4180 record into fields
+///
+/// `lines` have their indent (`indent` characters) removed; the first one is
+/// line number `first`.
+pub(crate) fn parse_record(
+    lines: &[String],
+    first: usize,
+    indent: usize,
+) -> Result<Record, ParseError> {
+    let error = |line: usize, i: usize, message: &str| ParseError {
+        line: first + line,
+        column: indent + i + 1,
+        message: message.to_string(),
+    };
+    let mut line = 0;
+    let mut chars: Vec<char> = lines[0].chars().collect();
+    let mut fields = Vec::new();
+    let mut positions = Vec::new();
+    let mut i = 0;
+
+    loop {
+        positions.push((first + line, indent + i + 1));
+        if chars.get(i) == Some(&'"') {
+            let open = (line, i);
+            let mut value = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => {
+                        line += 1;
+                        let Some(next) = lines.get(line) else {
+                            return Err(error(open.0, open.1, "unterminated quoted field"));
+                        };
+                        chars = next.chars().collect();
+                        value.push('\n');
+                        i = 0;
+                    }
+                    Some('"') if chars.get(i + 1) == Some(&'"') => {
+                        value.push('"');
+                        i += 2;
+                    }
+                    Some('"') => {
+                        i += 1;
+                        break;
+                    }
+                    Some(&c) => {
+                        value.push(c);
+                        i += 1;
+                    }
+                }
+            }
+            fields.push(Some(value));
+        } else {
+            let start = i;
+            while let Some(&c) = chars.get(i).filter(|&&c| c != ',') {
+                if c == '"' {
+                    return Err(error(line, i, "quote inside an unquoted field"));
+                }
+                i += 1;
+            }
+            let value: String = chars[start..i].iter().collect();
+            fields.push((!value.is_empty()).then_some(value));
+        }
+
+        match chars.get(i) {
+            None => break,
+            Some(',') => i += 1,
+            Some(_) => return Err(error(line, i, "expected ',' after a quoted field")),
+        }
+    }
+
+    Ok(Record {
+        fields,
+        positions,
+        end: (first + line, indent + i + 1),
+    })
+}
+
+/// Reads a TOSE document back into rows
+///
+/// Metadata lines, a document header and its preamble are skipped. Legends are
+/// applied, so dictionary-encoded columns come back decoded, and vertical
+/// blocks are transposed back into records. The child block of each record of a
+/// nested result is returned by [`ToseReader::children`], and folded constant
+/// columns are only reported by [`Header::constants`]. The rows of a multi-entity document are read one
+/// entity at a time with [`ToseReader::next_entity`].
+///
+/// Errors carry a [`ParseError`] with the position of the problem.
+pub struct ToseReader<R> {
+    lines: Lines<BufReader<R>>,
+    header: Header,
+    metadata: Vec<(String, String)>,
+    /// Column names; the field list unless the block is vertical
+    columns: Vec<String>,
+    legends: Vec<(usize, Legend)>,
+    /// Line of the schema header
+    header_line: usize,
+    /// Records read so far
+    read: usize,
+    /// Transposed records of a vertical block
+    buffered: VecDeque<Row>,
+    /// Fields of the child blocks of a nested result
+    child_columns: Vec<String>,
+    /// Child records of the row last returned
+    children: Vec<Row>,
+    done: bool,
+}
+
+impl<R: Read> ToseReader<R> {
+    /// Read up to the first data row of the document
+    pub fn new(input: R) -> io::Result<Self> {
+        let lines = Lines {
+            input: BufReader::new(input),
+            number: 0,
+            peeked: None,
+        };
+        Self::start(lines)
+    }
+
+    /// Finish this entity and start reading the next one of a multi-entity
+    /// document, if there is one
+    pub fn next_entity(mut self) -> io::Result<Option<Self>> {
+        for row in self.by_ref() {
+            row?;
+        }
+        loop {
+            match self.lines.peek()? {
+                None => return Ok(None),
+                Some("") => {
+                    self.lines.next()?;
+                }
+                Some(_) => return Self::start(self.lines).map(Some),
+            }
+        }
+    }
+
+    /// The schema header of the current entity
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Column names of the rows (the original columns of a vertical block)
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// Fields of the child blocks of a nested result, once a record has been
+    /// read
+    pub fn child_columns(&self) -> &[String] {
+        &self.child_columns
+    }
+
+    /// Child records of the row last returned, empty unless the result is
+    /// nested
+    pub fn children(&self) -> &[Row] {
+        &self.children
+    }
+
+    /// `# key: value` metadata lines written before the header
+    pub fn metadata(&self) -> &[(String, String)] {
+        &self.metadata
+    }
+
+    /// Skip metadata and document lines, then read the schema header and legends
+    fn start(mut lines: Lines<BufReader<R>>) -> io::Result<Self> {
+        let mut metadata = Vec::new();
+        let line = loop {
+            let Some(line) = lines.next()? else {
+                return Err(invalid(lines.number + 1, 1, "missing schema header"));
+            };
+            if let Some(entry) = line.strip_prefix("# ") {
+                let (key, value) = entry.split_once(':').unwrap_or((entry, ""));
+                metadata.push((key.to_string(), value.trim_start().to_string()));
+            } else if !(line.is_empty() || line.starts_with("@document[") || line.starts_with("> "))
+            {
+                break line;
+            }
+        };
+        let header_line = lines.number;
+        let header = Header::parse(&line)
+            .map_err(|(pos, message)| invalid(header_line, column_of(&line, pos), message))?;
+
+        let mut reader = ToseReader {
+            lines,
+            columns: header.fields.clone(),
+            header,
+            metadata,
+            legends: Vec::new(),
+            header_line,
+            read: 0,
+            buffered: VecDeque::new(),
+            child_columns: Vec::new(),
+            children: Vec::new(),
+            done: false,
+        };
+
+        let mut legends = Vec::new();
+        for column in reader.header.dictionary.clone() {
+            let Some(line) = reader.lines.next()? else {
+                return Err(reader.missing(format!("missing legend for column {}", column)));
+            };
+            let legend = Legend::parse(&column, &line).map_err(|(pos, message)| {
+                invalid(reader.lines.number, column_of(&line, pos), message)
+            })?;
+            legends.push((reader.lines.number, legend));
+        }
+
+        if reader.header.vertical {
+            reader.read_vertical(legends)?;
+        } else {
+            for (line, legend) in legends {
+                let col = reader
+                    .columns
+                    .iter()
+                    .position(|c| *c == legend.column)
+                    .ok_or_else(|| {
+                        invalid(
+                            line,
+                            1,
+                            format!("legend for unknown column {}", legend.column),
+                        )
+                    })?;
+                reader.legends.push((col, legend));
+            }
+        }
+        Ok(reader)
+    }
+
+    /// Error for input that ends before the document is complete
+    fn missing(&self, message: impl Into<String>) -> io::Error {
+        invalid(self.lines.number + 1, 1, message)
+    }
+
+    /// Read one RFC 4180 record whose lines start with `indent`, following
+    /// quoted line breaks onto further lines
+    fn record(&mut self, indent: &str) -> io::Result<Record> {
+        let Some(line) = self.lines.next()? else {
+            return Err(self.missing(format!(
+                "header declares {} records, found {}",
+                self.header.shown, self.read
+            )));
+        };
+        let first = self.lines.number;
+        let mut lines = vec![self.strip_indent(line, indent)?];
+        while continues(&lines) {
+            let Some(line) = self.lines.next()? else {
+                break;
+            };
+            lines.push(self.strip_indent(line, indent)?);
+        }
+        Ok(parse_record(&lines, first, indent.len())?)
+    }
+
+    /// A record line without its indent
+    fn strip_indent(&self, line: String, indent: &str) -> io::Result<String> {
+        match line.strip_prefix(indent) {
+            Some(rest) => Ok(rest.to_string()),
+            None => Err(invalid(
+                self.lines.number,
+                1,
+                "expected a child record indented by two spaces",
+            )),
+        }
+    }
+
+    /// Check that a record has `expected` fields
+    fn check_fields(record: &Record, expected: usize) -> io::Result<()> {
+        let found = record.fields.len();
+        if found == expected {
+            return Ok(());
+        }
+        let (line, column) = record
+            .positions
+            .get(expected)
+            .copied()
+            .unwrap_or(record.end);
+        Err(invalid(
+            line,
+            column,
+            format!("expected {} fields, found {}", expected, found),
+        ))
+    }
+
+    /// Replace dictionary codes in a record with their values
+    fn decode(legend: &Legend, record: &mut Record, col: usize) -> io::Result<()> {
+        let Some(code) = record.fields[col].as_deref() else {
+            return Ok(());
+        };
+        let value = legend.decode(code).ok_or_else(|| {
+            let (line, column) = record.positions[col];
+            invalid(
+                line,
+                column,
+                format!(
+                    "unknown code {} for dictionary column {}",
+                    code, legend.column
+                ),
+            )
+        })?;
+        record.fields[col] = Some(value.to_string());
+        Ok(())
+    }
+
+    /// Read a vertical block, one line per column, and transpose it into records
+    fn read_vertical(&mut self, legends: Vec<(usize, Legend)>) -> io::Result<()> {
+        let expected = self.header.shown + 1;
+        if self.header.fields.len() != expected {
+            return Err(invalid(
+                self.header_line,
+                1,
+                format!(
+                    "vertical header for {} records must list {} fields",
+                    self.header.shown, expected
+                ),
+            ));
+        }
+
+        let mut columns = Vec::new();
+        let mut lines = Vec::new();
+        while self
+            .lines
+            .peek()?
+            .is_some_and(|line| !line.is_empty() && !line.starts_with("# truncated:"))
+        {
+            let mut record = self.record("")?;
+            Self::check_fields(&record, expected)?;
+            let Some(name) = record.fields[0].clone() else {
+                let (line, column) = record.positions[0];
+                return Err(invalid(line, column, "missing column name"));
+            };
+            if let Some((_, legend)) = legends.iter().find(|(_, l)| l.column == name) {
+                for col in 1..expected {
+                    Self::decode(legend, &mut record, col)?;
+                }
+            }
+            columns.push(name);
+            lines.push(record.fields);
+        }
+        if let Some((line, legend)) = legends.iter().find(|(_, l)| !columns.contains(&l.column)) {
+            return Err(invalid(
+                *line,
+                1,
+                format!("legend for unknown column {}", legend.column),
+            ));
+        }
+
+        self.buffered = (1..expected)
+            .map(|col| lines.iter().map(|line| line[col].clone()).collect())
+            .collect();
+        self.columns = columns;
+        self.read = self.header.shown;
+        Ok(())
+    }
+
+    /// Read the next record of a table block and its child block
+    fn next_row(&mut self) -> io::Result<Row> {
+        let mut record = self.record("")?;
+        self.read += 1;
+        Self::check_fields(&record, self.header.fields.len())?;
+        for (col, legend) in &self.legends {
+            Self::decode(legend, &mut record, *col)?;
+        }
+        if let Some(entity) = self.header.nested.clone() {
+            self.read_children(&entity)?;
+        }
+        Ok(record.fields)
+    }
+
+    /// Read the indented child block that follows a parent record
+    fn read_children(&mut self, entity: &str) -> io::Result<()> {
+        let Some(line) = self.lines.next()? else {
+            return Err(self.missing(format!("missing {} child block", entity)));
+        };
+        let number = self.lines.number;
+        let Some(text) = line.strip_prefix(INDENT) else {
+            return Err(invalid(
+                number,
+                1,
+                format!("expected the indented {} child block", entity),
+            ));
+        };
+        let offset = INDENT.len();
+        let child = Header::parse(text)
+            .map_err(|(pos, message)| invalid(number, column_of(text, pos) + offset, message))?;
+        if child.entity != entity {
+            return Err(invalid(
+                number,
+                offset + 1,
+                format!("expected child entity {}, found {}", entity, child.entity),
+            ));
+        }
+        if self.read == 1 {
+            self.child_columns = child.fields;
+        } else if child.fields != self.child_columns {
+            return Err(invalid(
+                number,
+                offset + child.entity.len() + 1,
+                format!(
+                    "child fields {{{}}} differ from the first block's {{{}}}",
+                    child.fields.join(","),
+                    self.child_columns.join(",")
+                ),
+            ));
+        }
+        self.children.clear();
+        for _ in 0..child.shown {
+            let record = self.record(INDENT)?;
+            Self::check_fields(&record, self.child_columns.len())?;
+            self.children.push(record.fields);
+        }
+        Ok(())
+    }
+
+    /// After the last record: consume the truncation marker and check that no
+    /// records follow
+    fn finish(&mut self) -> io::Result<()> {
+        let omitted = self.header.total - self.header.shown;
+        if let Some(line) = self.lines.peek()?
+            && line.starts_with("# truncated:")
+        {
+            let expected = format!("# truncated: {} more rows not shown", omitted);
+            let valid = omitted > 0 && line == expected;
+            self.lines.next()?;
+            if !valid {
+                return Err(invalid(
+                    self.lines.number,
+                    1,
+                    format!(
+                        "truncation marker does not match the header (expected {:?})",
+                        expected
+                    ),
+                ));
+            }
+        }
+        match self.lines.peek()? {
+            None | Some("") => Ok(()),
+            Some(_) => Err(invalid(
+                self.lines.number + 1,
+                1,
+                format!(
+                    "more records than the {} the header declares",
+                    self.header.shown
+                ),
+            )),
+        }
+    }
+}
+
+impl<R: Read> Iterator for ToseReader<R> {
+    type Item = io::Result<Row>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        if let Some(row) = self.buffered.pop_front() {
+            return Some(Ok(row));
+        }
+
+        let result = if self.read < self.header.shown {
+            self.next_row().map(Some)
+        } else {
+            self.finish().map(|()| None)
+        };
+        match result {
+            Ok(Some(row)) => Some(Ok(row)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_header_with_qualifiers() {
+        let header = Header::parse(
+            "orders[2 of 30, sample=stratified(status,seed=7), deduped from 40, dict=status]{id,status,_count}|tenant=\"a,b\",deleted_at=NULL:",
+        )
+        .unwrap();
+        assert_eq!(header.entity, "orders");
+        assert_eq!((header.shown, header.total), (2, 30));
+        assert_eq!(header.sample.as_deref(), Some("stratified(status,seed=7)"));
+        assert_eq!(header.deduped_from, Some(40));
+        assert_eq!(header.dictionary, vec!["status"]);
+        assert_eq!(header.fields, vec!["id", "status", "_count"]);
+        assert_eq!(
+            header.constants,
+            vec![
+                ("tenant".to_string(), Some("a,b".to_string())),
+                ("deleted_at".to_string(), None)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_header_error_positions() {
+        assert_eq!(Header::parse("us-ers[1]{id}:").unwrap_err().0, 2);
+        assert_eq!(Header::parse("users[x]{id}:").unwrap_err().0, 6);
+        assert_eq!(Header::parse("users[1, nested]{id}:").unwrap_err().0, 9);
+        assert_eq!(Header::parse("users[1]{id}").unwrap_err().0, 12);
+        assert_eq!(Header::parse("users[3 of 2]{id}:").unwrap_err().0, 6);
+    }
+
+    #[test]
+    fn test_quoted_fields_and_nulls() {
+        let input = "t[2]{a,b,c}:\n\"x, \"\"y\"\"\",,\"\"\n\"line 1\nline 2\",2,3\n";
+        let rows: Vec<Row> = ToseReader::new(input.as_bytes())
+            .unwrap()
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                vec![Some("x, \"y\"".to_string()), None, Some(String::new())],
+                vec![
+                    Some("line 1\nline 2".to_string()),
+                    Some("2".to_string()),
+                    Some("3".to_string())
+                ],
+            ]
+        );
+    }
+
+    fn first_error(input: &str) -> ParseError {
+        let err = ToseReader::new(input.as_bytes())
+            .and_then(|reader| reader.collect::<io::Result<Vec<_>>>())
+            .unwrap_err();
+        err.get_ref()
+            .and_then(|inner| inner.downcast_ref::<ParseError>())
+            .cloned()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_error_positions() {
+        let err = first_error("t[2]{a,b}:\n1,2\n3\n");
+        assert_eq!((err.line, err.column), (3, 2));
+        assert_eq!(err.message, "expected 2 fields, found 1");
+
+        let err = first_error("t[1]{a,b}:\n1,\"open\n");
+        assert_eq!((err.line, err.column), (2, 3));
+
+        let err = first_error("t[1]{a}:\nab\"c\n");
+        assert_eq!((err.line, err.column), (2, 3));
+
+        let err = first_error("t[1]{a}:\n1\n2\n");
+        assert_eq!((err.line, err.column), (3, 1));
+
+        let err = first_error("t[3]{a}:\n1\n2\n");
+        assert_eq!(err.line, 4);
+        assert_eq!(err.message, "header declares 3 records, found 2");
+    }
+
+    #[test]
+    fn test_child_blocks() {
+        let input = "o[2, nested=items]{id}:\n1\n  items[2]{sku,qty}:\n  A1,2\n  B7,\n2\n  items[0]{sku,qty}:\n";
+        let mut reader = ToseReader::new(input.as_bytes()).unwrap();
+        assert_eq!(reader.next().unwrap().unwrap(), vec![Some("1".to_string())]);
+        assert_eq!(reader.child_columns(), ["sku", "qty"]);
+        assert_eq!(
+            reader.children(),
+            [
+                vec![Some("A1".to_string()), Some("2".to_string())],
+                vec![Some("B7".to_string()), None]
+            ]
+        );
+        assert_eq!(reader.next().unwrap().unwrap(), vec![Some("2".to_string())]);
+        assert!(reader.children().is_empty());
+        assert!(reader.next().is_none());
+
+        let err =
+            first_error("o[2, nested=items]{id}:\n1\n  items[0]{sku}:\n2\n  items[0]{qty}:\n");
+        assert_eq!((err.line, err.column), (5, 8));
+        assert_eq!(
+            err.message,
+            "child fields {qty} differ from the first block's {sku}"
+        );
+    }
+}
@@ -0,0 +1,271 @@
+use std::io::{self, Read, Write};
+
+use crate::PsqlTable;
+use crate::reader::{Row, ToseReader};
+
+/// Write every entity of a TOSE document as a psql aligned table, separated by
+/// empty lines
+///
+/// Numeric columns are right-aligned, others left-aligned, and multi-line
+/// values continue on the next line with a `+` marker, as psql prints them.
+/// Folded constant columns are restored, and nested results are flattened
+/// into one row per child record.
+pub fn render_psql<R: Read, W: Write>(input: R, mut output: W) -> io::Result<()> {
+    let mut entity = Some(ToseReader::new(input)?);
+    let mut first = true;
+    while let Some(mut reader) = entity {
+        let (columns, rows) = flatten(&mut reader)?;
+        if !first {
+            writeln!(output)?;
+        }
+        output.write_all(table(&columns, &rows).as_bytes())?;
+        first = false;
+        entity = reader.next_entity()?;
+    }
+    output.flush()
+}
+
+/// Render every entity of a TOSE document and parse the table back as psql
+/// output, failing unless every column and row of the entity comes back,
+/// including folded constants and child records
+///
+/// Values with line breaks or `|`, and empty strings (shown like NULL), do
+/// not survive the round trip.
+pub fn check_psql_round_trip<R: Read>(input: R) -> io::Result<()> {
+    let mut entity = Some(ToseReader::new(input)?);
+    while let Some(mut reader) = entity {
+        let name = reader.header().entity.clone();
+        let (columns, rows) = flatten(&mut reader)?;
+        let mismatch = |message: String| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Round trip failed for {}: {}", name, message),
+            )
+        };
+        let parsed = PsqlTable::parse(table(&columns, &rows).as_bytes())
+            .map_err(|err| mismatch(err.to_string()))?;
+        if parsed.columns != columns {
+            return Err(mismatch(format!(
+                "columns read back as {:?}",
+                parsed.columns
+            )));
+        }
+        if parsed.rows.len() != rows.len() {
+            return Err(mismatch(format!(
+                "{} rows read back as {}",
+                rows.len(),
+                parsed.rows.len()
+            )));
+        }
+        for (idx, (row, back)) in rows.iter().zip(&parsed.rows).enumerate() {
+            if row.iter().any(|cell| cell.as_deref() == Some("")) {
+                return Err(mismatch(format!(
+                    "row {} holds an empty string, which reads back as NULL",
+                    idx + 1
+                )));
+            }
+            let expected: Vec<&str> = row
+                .iter()
+                .map(|cell| cell.as_deref().unwrap_or_default())
+                .collect();
+            if expected != *back {
+                return Err(mismatch(format!("row {} read back as {:?}", idx + 1, back)));
+            }
+        }
+        entity = reader.next_entity()?;
+    }
+    Ok(())
+}
+
+/// All columns and rows of an entity: folded constants follow the fields, and
+/// each child record follows the fields of its parent
+///
+/// A parent without child records keeps one row, with the child columns NULL,
+/// as the outer join it came from produced it.
+fn flatten<R: Read>(reader: &mut ToseReader<R>) -> io::Result<(Vec<String>, Vec<Row>)> {
+    let header = reader.header().clone();
+    let constants: Vec<Option<String>> = header
+        .constants
+        .iter()
+        .map(|(_, value)| value.clone())
+        .collect();
+    let mut rows = Vec::new();
+    while let Some(row) = reader.next() {
+        let mut row = row?;
+        row.extend(constants.iter().cloned());
+        if header.nested.is_none() {
+            rows.push(row);
+            continue;
+        }
+        let children = reader.children();
+        if children.is_empty() {
+            let nulls = reader.child_columns().len();
+            rows.push(row.into_iter().chain(vec![None; nulls]).collect());
+        } else {
+            for child in children {
+                rows.push(row.iter().chain(child).cloned().collect());
+            }
+        }
+    }
+
+    let mut columns = reader.columns().to_vec();
+    columns.extend(header.constants.into_iter().map(|(column, _)| column));
+    columns.extend(reader.child_columns().iter().cloned());
+    Ok((columns, rows))
+}
+
+/// Format one psql aligned table with its `(N rows)` footer
+fn table(columns: &[String], rows: &[Row]) -> String {
+    let cells: Vec<Vec<Vec<&str>>> = rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|cell| cell.as_deref().unwrap_or_default().split('\n').collect())
+                .collect()
+        })
+        .collect();
+    let widths: Vec<usize> = columns
+        .iter()
+        .enumerate()
+        .map(|(col, name)| {
+            cells
+                .iter()
+                .flat_map(|row| row[col].iter())
+                .map(|line| width(line))
+                .chain([width(name)])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+    let numeric: Vec<bool> = (0..columns.len())
+        .map(|col| {
+            rows.iter().all(|row| {
+                row[col]
+                    .as_deref()
+                    .is_none_or(|value| value.parse::<f64>().is_ok())
+            })
+        })
+        .collect();
+
+    let mut table = String::new();
+    let header: Vec<String> = columns
+        .iter()
+        .zip(&widths)
+        .map(|(name, &w)| {
+            let left = (w - width(name)) / 2;
+            format!(
+                " {}{}{} ",
+                " ".repeat(left),
+                name,
+                " ".repeat(w - width(name) - left)
+            )
+        })
+        .collect();
+    table.push_str(&header.join("|"));
+    table.push('\n');
+    let separator: Vec<String> = widths.iter().map(|w| "-".repeat(w + 2)).collect();
+    table.push_str(&separator.join("+"));
+    table.push('\n');
+
+    for row in &cells {
+        let height = row.iter().map(Vec::len).max().unwrap_or(1);
+        for line in 0..height {
+            let mut text = String::new();
+            for (col, cell) in row.iter().enumerate() {
+                let value = cell.get(line).copied().unwrap_or_default();
+                let pad = " ".repeat(widths[col] - width(value));
+                let continues = line + 1 < cell.len();
+                if col > 0 {
+                    text.push('|');
+                }
+                text.push(' ');
+                if numeric[col] {
+                    text.push_str(&pad);
+                    text.push_str(value);
+                } else {
+                    text.push_str(value);
+                    text.push_str(&pad);
+                }
+                text.push(if continues { '+' } else { ' ' });
+            }
+            // psql leaves no padding after the last column
+            let trimmed = text.trim_end_matches(' ');
+            table.push_str(trimmed);
+            table.push('\n');
+        }
+    }
+
+    let noun = if rows.len() == 1 { "row" } else { "rows" };
+    table.push_str(&format!("({} {})\n", rows.len(), noun));
+    table
+}
+
+/// Display width of a value, counted in characters
+fn width(text: &str) -> usize {
+    text.chars().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    fn row(values: &[&str]) -> Row {
+        values
+            .iter()
+            .map(|v| (!v.is_empty()).then(|| v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_aligned_table() {
+        let table = table(
+            &strings(&["id", "name"]),
+            &[row(&["1", "Alice"]), row(&["10", ""])],
+        );
+        assert_eq!(
+            table,
+            " id | name  \n----+-------\n  1 | Alice\n 10 |\n(2 rows)\n"
+        );
+    }
+
+    #[test]
+    fn test_multi_line_values() {
+        let table = table(&strings(&["id", "note"]), &[row(&["1", "a\nbc"])]);
+        assert_eq!(
+            table,
+            " id | note \n----+------\n  1 | a   +\n    | bc\n(1 row)\n"
+        );
+    }
+
+    #[test]
+    fn test_round_trip_check() {
+        assert!(check_psql_round_trip("t[2]{id,name}:\n1,\"Smith, J.\"\n2,\n".as_bytes()).is_ok());
+        let err = check_psql_round_trip("t[1]{id,name}:\n1,a|b\n".as_bytes()).unwrap_err();
+        assert!(err.to_string().starts_with("Round trip failed for t"));
+        assert!(check_psql_round_trip("t[1]{id,name}:\n1,\"\"\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_restores_constants_and_children() {
+        let mut output = Vec::new();
+        render_psql("t[2]{id}|tenant=42:\n1\n2\n".as_bytes(), &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            " id | tenant \n----+--------\n  1 |     42\n  2 |     42\n(2 rows)\n"
+        );
+
+        let nested =
+            "o[2, nested=items]{id}:\n1\n  items[2]{sku}:\n  A1\n  B7\n2\n  items[0]{sku}:\n";
+        let mut output = Vec::new();
+        render_psql(nested.as_bytes(), &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            " id | sku \n----+-----\n  1 | A1\n  1 | B7\n  2 |\n(3 rows)\n"
+        );
+        assert!(check_psql_round_trip(nested.as_bytes()).is_ok());
+    }
+}
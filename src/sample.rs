@@ -0,0 +1,365 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+/// Seed used when none is given, so repeated runs pick the same rows
+pub const DEFAULT_SEED: u64 = 42;
+
+/// How to choose rows when only a sample of the result is emitted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SampleMethod {
+    /// The first rows in result order
+    Head,
+    /// The last rows in result order
+    Tail,
+    /// Half from the start and half from the end
+    HeadTail,
+    /// Uniform random selection without replacement
+    Uniform { seed: u64 },
+    /// Single-pass reservoir sampling, for input that is consumed as a stream
+    Reservoir { seed: u64 },
+    /// Uniform selection within each distinct value of `column`, proportional to its size
+    Stratified { column: String, seed: u64 },
+}
+
+impl SampleMethod {
+    /// Replace the seed of a random method (no effect on head/tail methods)
+    pub fn with_seed(self, seed: u64) -> Self {
+        match self {
+            SampleMethod::Uniform { .. } => SampleMethod::Uniform { seed },
+            SampleMethod::Reservoir { .. } => SampleMethod::Reservoir { seed },
+            SampleMethod::Stratified { column, .. } => SampleMethod::Stratified { column, seed },
+            other => other,
+        }
+    }
+
+    /// Check that the column a stratified sample groups by exists
+    pub(crate) fn check_columns(&self, columns: &[String]) -> io::Result<()> {
+        if let SampleMethod::Stratified { column, .. } = self {
+            stratified_column(column, columns)?;
+        }
+        Ok(())
+    }
+}
+
+/// Index of the column a stratified sample groups by
+fn stratified_column(column: &str, columns: &[String]) -> io::Result<usize> {
+    columns.iter().position(|c| c == column).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Unknown column for stratified sample: {}", column),
+        )
+    })
+}
+
+impl FromStr for SampleMethod {
+    type Err = io::Error;
+
+    /// Parse `head`, `tail`, `head+tail`, `uniform`, `reservoir` or `stratified:COLUMN`
+    fn from_str(s: &str) -> io::Result<Self> {
+        let seed = DEFAULT_SEED;
+        match s {
+            "head" => Ok(SampleMethod::Head),
+            "tail" => Ok(SampleMethod::Tail),
+            "head+tail" => Ok(SampleMethod::HeadTail),
+            "uniform" => Ok(SampleMethod::Uniform { seed }),
+            "reservoir" => Ok(SampleMethod::Reservoir { seed }),
+            _ => match s.strip_prefix("stratified:") {
+                Some(column) if !column.is_empty() => Ok(SampleMethod::Stratified {
+                    column: column.to_string(),
+                    seed,
+                }),
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "Unknown sample method: {} (expected head, tail, head+tail, uniform, reservoir or stratified:COLUMN)",
+                        s
+                    ),
+                )),
+            },
+        }
+    }
+}
+
+/// Renders the method as written in the schema header, e.g. `uniform(seed=42)`
+impl fmt::Display for SampleMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SampleMethod::Head => write!(f, "head"),
+            SampleMethod::Tail => write!(f, "tail"),
+            SampleMethod::HeadTail => write!(f, "head+tail"),
+            SampleMethod::Uniform { seed } => write!(f, "uniform(seed={})", seed),
+            SampleMethod::Reservoir { seed } => write!(f, "reservoir(seed={})", seed),
+            SampleMethod::Stratified { column, seed } => {
+                write!(f, "stratified({},seed={})", column, seed)
+            }
+        }
+    }
+}
+
+/// Select `size` rows using `method`, keeping the original row order
+pub(crate) fn sample_rows(
+    rows: Vec<Vec<String>>,
+    columns: &[String],
+    method: &SampleMethod,
+    size: usize,
+) -> io::Result<Vec<Vec<String>>> {
+    method.check_columns(columns)?;
+    if rows.len() <= size {
+        return Ok(rows);
+    }
+
+    let sampled = match method {
+        SampleMethod::Head => rows.into_iter().take(size).collect(),
+        SampleMethod::Tail => {
+            let skip = rows.len() - size;
+            rows.into_iter().skip(skip).collect()
+        }
+        SampleMethod::HeadTail => {
+            let head = size.div_ceil(2);
+            let tail_start = rows.len() - (size - head);
+            rows.into_iter()
+                .enumerate()
+                .filter(|(idx, _)| *idx < head || *idx >= tail_start)
+                .map(|(_, row)| row)
+                .collect()
+        }
+        SampleMethod::Uniform { seed } => {
+            let mut rng = Rng::new(*seed);
+            let keep = choose_indices(rows.len(), size, &mut rng);
+            select(rows, &keep)
+        }
+        SampleMethod::Reservoir { seed } => sample_stream(rows.into_iter(), size, *seed),
+        SampleMethod::Stratified { column, seed } => {
+            let col = stratified_column(column, columns)?;
+            let mut rng = Rng::new(*seed);
+            let keep = stratified_indices(&rows, col, size, &mut rng);
+            select(rows, &keep)
+        }
+    };
+
+    Ok(sampled)
+}
+
+/// Reservoir-sample `size` rows in a single pass without buffering the
+/// input, keeping the original row order
+pub(crate) fn sample_stream(
+    rows: impl Iterator<Item = Vec<String>>,
+    size: usize,
+    seed: u64,
+) -> Vec<Vec<String>> {
+    let mut rng = Rng::new(seed);
+    let mut picked = reservoir(rows.enumerate(), size, &mut rng);
+    picked.sort_by_key(|(idx, _)| *idx);
+    picked.into_iter().map(|(_, row)| row).collect()
+}
+
+/// Keep the rows at the given ascending indices
+fn select(rows: Vec<Vec<String>>, keep: &[usize]) -> Vec<Vec<String>> {
+    let mut keep = keep.iter().peekable();
+    rows.into_iter()
+        .enumerate()
+        .filter(|(idx, _)| keep.next_if_eq(&idx).is_some())
+        .map(|(_, row)| row)
+        .collect()
+}
+
+/// Choose `size` distinct indices out of `0..len`, returned in ascending order
+fn choose_indices(len: usize, size: usize, rng: &mut Rng) -> Vec<usize> {
+    // Partial Fisher-Yates shuffle
+    let mut indices: Vec<usize> = (0..len).collect();
+    for i in 0..size.min(len) {
+        let j = i + rng.below(len - i);
+        indices.swap(i, j);
+    }
+    indices.truncate(size);
+    indices.sort_unstable();
+    indices
+}
+
+/// Algorithm R: a uniform sample of `size` items in a single pass
+fn reservoir<T>(items: impl Iterator<Item = T>, size: usize, rng: &mut Rng) -> Vec<T> {
+    let mut picked = Vec::with_capacity(size);
+    for (seen, item) in items.enumerate() {
+        if picked.len() < size {
+            picked.push(item);
+        } else {
+            let j = rng.below(seen + 1);
+            if j < size {
+                picked[j] = item;
+            }
+        }
+    }
+    picked
+}
+
+/// Allocate `size` across the distinct values of column `col` proportionally,
+/// handing the rounding remainder to the smallest strata first
+fn stratified_indices(rows: &[Vec<String>], col: usize, size: usize, rng: &mut Rng) -> Vec<usize> {
+    // Strata in order of first appearance
+    let mut order: Vec<&str> = Vec::new();
+    let mut strata: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (idx, row) in rows.iter().enumerate() {
+        let key = row[col].as_str();
+        strata
+            .entry(key)
+            .or_insert_with(|| {
+                order.push(key);
+                Vec::new()
+            })
+            .push(idx);
+    }
+
+    let total = rows.len();
+    let mut quotas: Vec<usize> = order
+        .iter()
+        .map(|key| strata[key].len() * size / total)
+        .collect();
+
+    // Hand out the remainder, smallest strata first so rare values are represented
+    let mut by_size: Vec<usize> = (0..order.len()).collect();
+    by_size.sort_by_key(|&i| strata[order[i]].len());
+    let mut remaining = size - quotas.iter().sum::<usize>();
+    while remaining > 0 {
+        let mut progressed = false;
+        for &i in &by_size {
+            if remaining > 0 && quotas[i] < strata[order[i]].len() {
+                quotas[i] += 1;
+                remaining -= 1;
+                progressed = true;
+            }
+        }
+        if !progressed {
+            break;
+        }
+    }
+
+    let mut keep: Vec<usize> = Vec::with_capacity(size);
+    for (i, key) in order.iter().enumerate() {
+        let members = &strata[key];
+        let picked = choose_indices(members.len(), quotas[i], rng);
+        keep.extend(picked.into_iter().map(|p| members[p]));
+    }
+    keep.sort_unstable();
+    keep
+}
+
+/// Small deterministic PRNG (SplitMix64); sampling only needs reproducibility
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in `0..bound`
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(n: usize) -> Vec<Vec<String>> {
+        (0..n)
+            .map(|i| {
+                vec![
+                    i.to_string(),
+                    if i % 10 == 0 { "rare" } else { "common" }.to_string(),
+                ]
+            })
+            .collect()
+    }
+
+    fn ids(rows: &[Vec<String>]) -> Vec<usize> {
+        rows.iter().map(|r| r[0].parse().unwrap()).collect()
+    }
+
+    fn columns() -> Vec<String> {
+        vec!["id".to_string(), "kind".to_string()]
+    }
+
+    #[test]
+    fn test_parse_methods() {
+        assert_eq!(
+            "head+tail".parse::<SampleMethod>().unwrap(),
+            SampleMethod::HeadTail
+        );
+        assert_eq!(
+            "stratified:kind"
+                .parse::<SampleMethod>()
+                .unwrap()
+                .with_seed(7),
+            SampleMethod::Stratified {
+                column: "kind".to_string(),
+                seed: 7
+            }
+        );
+        assert!("random".parse::<SampleMethod>().is_err());
+    }
+
+    #[test]
+    fn test_head_tail() {
+        let sampled = sample_rows(rows(10), &columns(), &SampleMethod::HeadTail, 5).unwrap();
+        assert_eq!(ids(&sampled), vec![0, 1, 2, 8, 9]);
+        let sampled = sample_rows(rows(10), &columns(), &SampleMethod::Tail, 2).unwrap();
+        assert_eq!(ids(&sampled), vec![8, 9]);
+    }
+
+    #[test]
+    fn test_uniform_is_reproducible_and_ordered() {
+        let method = SampleMethod::Uniform { seed: 1 };
+        let a = ids(&sample_rows(rows(1000), &columns(), &method, 20).unwrap());
+        let b = ids(&sample_rows(rows(1000), &columns(), &method, 20).unwrap());
+        assert_eq!(a, b);
+        assert_eq!(a.len(), 20);
+        assert!(a.windows(2).all(|w| w[0] < w[1]));
+        assert_ne!(a, (0..20).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_reservoir_size() {
+        let method = SampleMethod::Reservoir { seed: 3 };
+        let sampled = ids(&sample_rows(rows(500), &columns(), &method, 25).unwrap());
+        assert_eq!(sampled.len(), 25);
+        assert!(sampled.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_stratified_keeps_rare_values() {
+        let method = SampleMethod::Stratified {
+            column: "kind".to_string(),
+            seed: 5,
+        };
+        let sampled = sample_rows(rows(1000), &columns(), &method, 20).unwrap();
+        let rare = sampled.iter().filter(|r| r[1] == "rare").count();
+        assert_eq!(sampled.len(), 20);
+        assert_eq!(rare, 2);
+    }
+
+    #[test]
+    fn test_stratified_unknown_column() {
+        let method = SampleMethod::Stratified {
+            column: "missing".to_string(),
+            seed: 5,
+        };
+        assert!(sample_rows(rows(10), &columns(), &method, 2).is_err());
+        // Also when every row fits and nothing is sampled
+        let err = sample_rows(rows(2), &columns(), &method, 10).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unknown column for stratified sample: missing"
+        );
+    }
+}
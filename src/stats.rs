@@ -0,0 +1,221 @@
+use std::fmt;
+
+use crate::PsqlTable;
+use crate::tokenizer::TokenCounter;
+
+/// Size measurements for one output format
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatStats {
+    /// Format name (`tose`, `json`, `jsonl`, `csv` or `psql`)
+    pub format: &'static str,
+    pub bytes: usize,
+    pub rows: usize,
+    pub tokens: usize,
+}
+
+/// Comparison of the TOSE output against other common encodings of the same rows
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConversionStats {
+    /// True if token counts come from a BPE vocabulary rather than the chars/4 estimate
+    pub bpe: bool,
+    pub formats: Vec<FormatStats>,
+}
+
+impl ConversionStats {
+    /// Measure the TOSE output alongside JSON, JSONL, CSV and the original psql text
+    pub(crate) fn measure(
+        counter: &TokenCounter,
+        tose: &str,
+        psql: &str,
+        columns: &[String],
+        rows: &[Vec<String>],
+    ) -> Self {
+        let renderings = [
+            ("tose", tose.to_string()),
+            ("json", render_json(columns, rows)),
+            ("jsonl", render_jsonl(columns, rows)),
+            ("csv", render_csv(columns, rows)),
+            ("psql", psql.to_string()),
+        ];
+
+        let formats = renderings
+            .iter()
+            .map(|(format, text)| FormatStats {
+                format,
+                bytes: text.len(),
+                rows: rows.len(),
+                tokens: counter.count(text),
+            })
+            .collect();
+
+        ConversionStats {
+            bpe: counter.is_bpe(),
+            formats,
+        }
+    }
+
+    /// Look up the measurements for one format
+    pub fn get(&self, format: &str) -> Option<&FormatStats> {
+        self.formats.iter().find(|f| f.format == format)
+    }
+}
+
+impl fmt::Display for ConversionStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let method = if self.bpe {
+            "bpe"
+        } else {
+            "estimated (chars/4)"
+        };
+        writeln!(f, "tokens: {}", method)?;
+        writeln!(
+            f,
+            "{:<6} {:>10} {:>8} {:>10} {:>7}",
+            "format", "bytes", "rows", "tokens", "vs tose"
+        )?;
+
+        let tose_tokens = self.get("tose").map_or(0, |s| s.tokens);
+        for stats in &self.formats {
+            let ratio = if tose_tokens == 0 {
+                "-".to_string()
+            } else {
+                format!("{:.2}x", stats.tokens as f64 / tose_tokens as f64)
+            };
+            writeln!(
+                f,
+                "{:<6} {:>10} {:>8} {:>10} {:>7}",
+                stats.format, stats.bytes, stats.rows, stats.tokens, ratio
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Render rows as a JSON array of objects
+fn render_json(columns: &[String], rows: &[Vec<String>]) -> String {
+    let objects: Vec<String> = rows.iter().map(|row| json_object(columns, row)).collect();
+    format!("[{}]\n", objects.join(","))
+}
+
+/// Render rows as one JSON object per line
+fn render_jsonl(columns: &[String], rows: &[Vec<String>]) -> String {
+    rows.iter()
+        .map(|row| json_object(columns, row) + "\n")
+        .collect()
+}
+
+/// Render rows as CSV with a header line
+fn render_csv(columns: &[String], rows: &[Vec<String>]) -> String {
+    let mut out = String::new();
+    for line in std::iter::once(columns).chain(rows.iter().map(Vec::as_slice)) {
+        let fields: Vec<String> = line
+            .iter()
+            .map(|f| PsqlTable::escape_csv_field(f))
+            .collect();
+        out.push_str(&fields.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+fn json_object(columns: &[String], row: &[String]) -> String {
+    let members: Vec<String> = columns
+        .iter()
+        .zip(row)
+        .map(|(column, value)| format!("{}:{}", json_string(column), json_value(value)))
+        .collect();
+    format!("{{{}}}", members.join(","))
+}
+
+/// Encode a psql cell as JSON: empty is null, numbers stay bare
+fn json_value(value: &str) -> String {
+    if value.is_empty() {
+        "null".to_string()
+    } else if is_json_number(value) {
+        value.to_string()
+    } else {
+        json_string(value)
+    }
+}
+
+fn is_json_number(value: &str) -> bool {
+    let digits = value.strip_prefix('-').unwrap_or(value);
+    let (int, frac) = match digits.split_once('.') {
+        Some((int, frac)) => (int, Some(frac)),
+        None => (digits, None),
+    };
+    let all_digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    all_digits(int) && (int == "0" || !int.starts_with('0')) && frac.is_none_or(all_digits)
+}
+
+fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> (Vec<String>, Vec<Vec<String>>) {
+        let columns = vec!["id".to_string(), "name".to_string()];
+        let rows = vec![
+            vec!["1".to_string(), "Alice".to_string()],
+            vec!["2".to_string(), String::new()],
+        ];
+        (columns, rows)
+    }
+
+    #[test]
+    fn test_render_json() {
+        let (columns, rows) = sample();
+        assert_eq!(
+            render_json(&columns, &rows),
+            "[{\"id\":1,\"name\":\"Alice\"},{\"id\":2,\"name\":null}]\n"
+        );
+    }
+
+    #[test]
+    fn test_render_csv_includes_header() {
+        let (columns, rows) = sample();
+        assert_eq!(render_csv(&columns, &rows), "id,name\n1,Alice\n2,\n");
+    }
+
+    #[test]
+    fn test_json_number_detection() {
+        assert!(is_json_number("42"));
+        assert!(is_json_number("-3.14"));
+        assert!(!is_json_number("007"));
+        assert!(!is_json_number("1e5x"));
+        assert!(!is_json_number("12."));
+    }
+
+    #[test]
+    fn test_measure_reports_every_format() {
+        let (columns, rows) = sample();
+        let stats = ConversionStats::measure(
+            &TokenCounter::heuristic(),
+            "result[2]{id,name}:\n1,Alice\n2,\n",
+            "",
+            &columns,
+            &rows,
+        );
+        let names: Vec<&str> = stats.formats.iter().map(|f| f.format).collect();
+        assert_eq!(names, vec!["tose", "json", "jsonl", "csv", "psql"]);
+        assert_eq!(stats.get("tose").unwrap().bytes, 31);
+        assert_eq!(stats.get("json").unwrap().rows, 2);
+    }
+}
@@ -0,0 +1,76 @@
+use std::io::{self, Read};
+
+use crate::PsqlTable;
+use crate::metadata::SourceInfo;
+use crate::reader::Row;
+
+/// A parsed query result, for transforming rows before writing them out
+///
+/// Cells are `None` where psql showed `NULL` (an empty cell).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    pub columns: Vec<String>,
+    pub rows: Vec<Row>,
+    pub source: SourceInfo,
+}
+
+impl Table {
+    /// Parse psql aligned output, or its first result when it holds several
+    pub fn parse<R: Read>(input: R) -> io::Result<Self> {
+        PsqlTable::parse(input).map(Table::from)
+    }
+}
+
+impl From<PsqlTable> for Table {
+    fn from(table: PsqlTable) -> Self {
+        let rows = table
+            .rows
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|cell| (!cell.is_empty()).then_some(cell))
+                    .collect()
+            })
+            .collect();
+        Table {
+            columns: table.columns,
+            rows,
+            source: table.source,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Dialect;
+
+    #[test]
+    fn test_parse_table_and_source() {
+        let input = "Timing is on.\nSELECT id, name FROM users;\n id | name\n----+------\n  1 | Alice\n  2 |\n(2 rows)\n\nTime: 0.412 ms\n";
+        let table = Table::parse(input.as_bytes()).unwrap();
+        assert_eq!(table.columns, ["id", "name"]);
+        assert_eq!(
+            table.rows,
+            [
+                vec![Some("1".to_string()), Some("Alice".to_string())],
+                vec![Some("2".to_string()), None],
+            ]
+        );
+        assert_eq!(table.source.dialect, Dialect::PsqlAligned);
+        assert_eq!(table.source.footer_count, Some(2));
+        assert_eq!(table.source.notices, ["Timing is on."]);
+        assert_eq!(
+            table.source.query.as_deref(),
+            Some("SELECT id, name FROM users;")
+        );
+        assert_eq!(table.source.time.as_deref(), Some("0.412 ms"));
+    }
+
+    #[test]
+    fn test_missing_footer() {
+        let table = Table::parse(" n\n---\n 1\n".as_bytes()).unwrap();
+        assert_eq!(table.source.footer_count, None);
+        assert_eq!(table.rows.len(), 1);
+    }
+}
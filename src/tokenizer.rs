@@ -0,0 +1,257 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Counts LLM tokens for a piece of text
+///
+/// Uses a byte-level BPE vocabulary when one is loaded, otherwise falls back
+/// to the common "4 characters per token" estimate.
+#[derive(Debug, Clone, Default)]
+pub struct TokenCounter {
+    ranks: Option<HashMap<Vec<u8>, u32>>,
+}
+
+impl TokenCounter {
+    /// Create a counter using the chars/4 heuristic
+    pub fn heuristic() -> Self {
+        TokenCounter { ranks: None }
+    }
+
+    /// Load a BPE vocabulary in tiktoken format (`<base64 token> <rank>` per line)
+    pub fn from_vocab_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let content = fs::read_to_string(path)?;
+        Self::from_vocab_str(&content)
+    }
+
+    /// Parse a BPE vocabulary in tiktoken format from a string
+    pub fn from_vocab_str(content: &str) -> io::Result<Self> {
+        let mut ranks = HashMap::new();
+        for (idx, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let invalid = || {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid vocabulary entry at line {}", idx + 1),
+                )
+            };
+            let (token, rank) = line.split_once(' ').ok_or_else(invalid)?;
+            let bytes = decode_base64(token).ok_or_else(invalid)?;
+            let rank: u32 = rank.trim().parse().map_err(|_| invalid())?;
+            ranks.insert(bytes, rank);
+        }
+
+        if ranks.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Vocabulary file contains no tokens",
+            ));
+        }
+
+        Ok(TokenCounter { ranks: Some(ranks) })
+    }
+
+    /// Whether counts come from a real BPE vocabulary
+    pub fn is_bpe(&self) -> bool {
+        self.ranks.is_some()
+    }
+
+    /// Count the tokens in `text`
+    pub fn count(&self, text: &str) -> usize {
+        match &self.ranks {
+            Some(ranks) => pre_tokenize(text)
+                .into_iter()
+                .map(|piece| bpe_count(piece.as_bytes(), ranks))
+                .sum(),
+            None => text.chars().count().div_ceil(4),
+        }
+    }
+}
+
+/// Split text into pieces the way GPT-style tokenizers do before merging
+///
+/// This approximates the cl100k pattern: words with an optional leading
+/// space, digit runs of up to three, punctuation runs and whitespace.
+fn pre_tokenize(text: &str) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let end_of = |i: usize| chars.get(i).map_or(text.len(), |&(pos, _)| pos);
+    let mut i = 0;
+
+    while i < chars.len() {
+        let start = chars[i].0;
+        let c = chars[i].1;
+        let next = chars.get(i + 1).map(|&(_, c)| c);
+
+        let mut j = i + 1;
+        if c.is_alphabetic()
+            || (!c.is_alphanumeric() && !c.is_whitespace() && next.is_some_and(char::is_alphabetic))
+            || (c == ' ' && next.is_some_and(char::is_alphabetic))
+        {
+            while j < chars.len() && chars[j].1.is_alphabetic() {
+                j += 1;
+            }
+        } else if c.is_numeric() {
+            while j < chars.len() && j - i < 3 && chars[j].1.is_numeric() {
+                j += 1;
+            }
+        } else if c == '\n' || c == '\r' {
+            while j < chars.len() && matches!(chars[j].1, '\n' | '\r') {
+                j += 1;
+            }
+        } else if c.is_whitespace() {
+            while j < chars.len()
+                && chars[j].1.is_whitespace()
+                && !matches!(chars[j].1, '\n' | '\r')
+            {
+                j += 1;
+            }
+        } else {
+            while j < chars.len() && !chars[j].1.is_alphanumeric() && !chars[j].1.is_whitespace() {
+                j += 1;
+            }
+        }
+
+        pieces.push(&text[start..end_of(j)]);
+        i = j;
+    }
+
+    pieces
+}
+
+/// Count the tokens of one pre-tokenized piece by applying rank-ordered merges
+fn bpe_count(piece: &[u8], ranks: &HashMap<Vec<u8>, u32>) -> usize {
+    if piece.is_empty() {
+        return 0;
+    }
+    if ranks.contains_key(piece) {
+        return 1;
+    }
+
+    let mut parts: Vec<&[u8]> = piece.chunks(1).collect();
+    while parts.len() > 1 {
+        let best = parts
+            .windows(2)
+            .enumerate()
+            .filter_map(|(idx, pair)| {
+                let start = pair[0].as_ptr() as usize - piece.as_ptr() as usize;
+                let merged = &piece[start..start + pair[0].len() + pair[1].len()];
+                ranks.get(merged).map(|&rank| (rank, idx, merged))
+            })
+            .min_by_key(|&(rank, _, _)| rank);
+
+        match best {
+            Some((_, idx, merged)) => {
+                parts[idx] = merged;
+                parts.remove(idx + 1);
+            }
+            None => break,
+        }
+    }
+
+    parts.len()
+}
+
+/// Decode standard base64 (with optional padding)
+fn decode_base64(input: &str) -> Option<Vec<u8>> {
+    fn value(c: u8) -> Option<u32> {
+        match c {
+            b'A'..=b'Z' => Some((c - b'A') as u32),
+            b'a'..=b'z' => Some((c - b'a' + 26) as u32),
+            b'0'..=b'9' => Some((c - b'0' + 52) as u32),
+            b'+' => Some(62),
+            b'/' => Some(63),
+            _ => None,
+        }
+    }
+
+    let trimmed = input.trim_end_matches('=').as_bytes();
+    let mut out = Vec::with_capacity(trimmed.len() * 3 / 4);
+    for chunk in trimmed.chunks(4) {
+        if chunk.len() == 1 {
+            return None;
+        }
+        let mut acc = 0u32;
+        for (i, &c) in chunk.iter().enumerate() {
+            acc |= value(c)? << (18 - 6 * i);
+        }
+        let bytes = acc.to_be_bytes();
+        out.extend_from_slice(&bytes[1..chunk.len()]);
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vocab(tokens: &[&str]) -> TokenCounter {
+        let mut content = String::new();
+        for byte in 0u8..=255 {
+            content.push_str(&format!("{} {}\n", encode(&[byte]), byte));
+        }
+        for (i, token) in tokens.iter().enumerate() {
+            content.push_str(&format!("{} {}\n", encode(token.as_bytes()), 256 + i));
+        }
+        TokenCounter::from_vocab_str(&content).unwrap()
+    }
+
+    fn encode(bytes: &[u8]) -> String {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut out = String::new();
+        for chunk in bytes.chunks(3) {
+            let mut buf = [0u8; 3];
+            buf[..chunk.len()].copy_from_slice(chunk);
+            let n = u32::from_be_bytes([0, buf[0], buf[1], buf[2]]);
+            for i in 0..4 {
+                if i <= chunk.len() {
+                    out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+                } else {
+                    out.push('=');
+                }
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn test_decode_base64() {
+        assert_eq!(decode_base64("aGVsbG8="), Some(b"hello".to_vec()));
+        assert_eq!(decode_base64("IQ=="), Some(b"!".to_vec()));
+        assert_eq!(decode_base64("not base64!"), None);
+    }
+
+    #[test]
+    fn test_heuristic_count() {
+        let counter = TokenCounter::heuristic();
+        assert!(!counter.is_bpe());
+        assert_eq!(counter.count(""), 0);
+        assert_eq!(counter.count("abcd"), 1);
+        assert_eq!(counter.count("abcde"), 2);
+    }
+
+    #[test]
+    fn test_bpe_merges_known_tokens() {
+        let counter = vocab(&["he", "ll", "hell", "hello", " wo", " world"]);
+        assert!(counter.is_bpe());
+        assert_eq!(counter.count("hello"), 1);
+        assert_eq!(counter.count("hello world"), 2);
+        assert_eq!(counter.count("xyz"), 3);
+    }
+
+    #[test]
+    fn test_pre_tokenize_splits_words_and_digits() {
+        assert_eq!(
+            pre_tokenize("id,name 12345"),
+            vec!["id", ",name", " ", "123", "45"]
+        );
+    }
+
+    #[test]
+    fn test_invalid_vocab_line() {
+        let err = TokenCounter::from_vocab_str("aGVsbG8=\n").unwrap_err();
+        assert!(err.to_string().contains("line 1"));
+    }
+}
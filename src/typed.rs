@@ -0,0 +1,710 @@
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::str::FromStr;
+
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, Visitor};
+use serde::ser::{self, Impossible, Serialize, SerializeMap, SerializeStruct};
+
+use crate::ToseWriter;
+use crate::reader::{Row, ToseReader};
+
+/// Write `rows` as one TOSE entity named `entity`
+///
+/// Each row must serialize as a struct (or a map with string keys) whose
+/// fields become the columns, in order. Rows are written by [`ToseWriter`]:
+/// names are limited to letters, digits and `_`, `None` and unit values become
+/// `NULL`, and empty strings are written as `""`. Unit enum variants are
+/// written by name. Nested structs, sequences and bytes are not supported.
+/// Since the columns are taken from the first row, an empty slice is an error.
+pub fn to_writer<W: Write, T: Serialize>(writer: W, entity: &str, rows: &[T]) -> io::Result<()> {
+    let invalid = |idx: usize, message: String| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("row {}: {}", idx + 1, message),
+        )
+    };
+    let mut records = rows.iter().enumerate().map(|(idx, row)| {
+        row.serialize(RowSerializer)
+            .map_err(|err| invalid(idx, err.to_string()))
+    });
+
+    // Columns come from the first row
+    let Some(first) = records.next().transpose()? else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("No rows to take the columns of {} from", entity),
+        ));
+    };
+    let fields = first.0.clone();
+    let columns: Vec<&str> = fields.iter().map(String::as_str).collect();
+    let mut tose = ToseWriter::new(writer, entity, &columns)?;
+    for (idx, record) in [Ok(first)].into_iter().chain(records).enumerate() {
+        let (names, cells) = record?;
+        if names != fields {
+            return Err(invalid(
+                idx,
+                format!(
+                    "fields {:?} differ from the first row's {:?}",
+                    names, fields
+                ),
+            ));
+        }
+        let cells: Vec<Option<&str>> = cells.iter().map(Option::as_deref).collect();
+        tose.write_row(&cells)?;
+    }
+    tose.finish().map(drop)
+}
+
+/// Read the rows of the first entity of a TOSE document
+///
+/// Columns are matched to struct fields by name. `NULL` reads as `None` for
+/// `Option` fields and as an empty string for string fields; booleans accept
+/// psql's `t`/`f` as well as `true`/`false`. Dictionary codes are decoded and
+/// folded constant columns restored.
+pub fn from_reader<R: Read, T: DeserializeOwned>(reader: R) -> io::Result<Vec<T>> {
+    let mut reader = ToseReader::new(reader)?;
+    let header = reader.header().clone();
+    let mut columns = reader.columns().to_vec();
+    columns.extend(header.constants.iter().map(|(column, _)| column.clone()));
+
+    let mut rows = Vec::new();
+    for (idx, row) in reader.by_ref().enumerate() {
+        let mut row = row?;
+        row.extend(header.constants.iter().map(|(_, value)| value.clone()));
+        let value = T::deserialize(RowDeserializer {
+            columns: &columns,
+            row,
+        })
+        .map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("row {}: {}", idx + 1, err),
+            )
+        })?;
+        rows.push(value);
+    }
+    Ok(rows)
+}
+
+/// Error raised while mapping a value to or from TOSE cells
+#[derive(Debug)]
+struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+fn unsupported<T>(what: &str) -> Result<T, Error> {
+    Err(Error(format!("{} cannot be written as a TOSE row", what)))
+}
+
+/// Rows must be structs or maps, not plain values
+macro_rules! reject_primitives {
+    ($($method:ident: $ty:ty),* $(,)?) => {
+        $(
+            fn $method(self, _v: $ty) -> Result<Self::Ok, Error> {
+                unsupported("a bare value")
+            }
+        )*
+    };
+}
+
+/// Plain values are written as their `Display` text
+macro_rules! format_primitives {
+    ($($method:ident: $ty:ty),* $(,)?) => {
+        $(
+            fn $method(self, v: $ty) -> Result<Self::Ok, Error> {
+                Ok(Some(v.to_string()))
+            }
+        )*
+    };
+}
+
+/// Serializes one row into its field names and cells
+struct RowSerializer;
+
+/// Fields collected from a struct or map
+#[derive(Default)]
+struct RowCells {
+    names: Vec<String>,
+    cells: Row,
+    /// Key of a map entry whose value has not been serialized yet
+    key: Option<String>,
+}
+
+impl RowCells {
+    fn push<T: Serialize + ?Sized>(&mut self, name: String, value: &T) -> Result<(), Error> {
+        let cell = value
+            .serialize(CellSerializer)
+            .map_err(|err| Error(format!("field {}: {}", name, err)))?;
+        self.names.push(name);
+        self.cells.push(cell);
+        Ok(())
+    }
+}
+
+impl SerializeStruct for RowCells {
+    type Ok = (Vec<String>, Row);
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.push(key.to_string(), value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        Ok((self.names, self.cells))
+    }
+}
+
+impl SerializeMap for RowCells {
+    type Ok = (Vec<String>, Row);
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        match key.serialize(CellSerializer)? {
+            Some(key) => {
+                self.key = Some(key);
+                Ok(())
+            }
+            None => unsupported("a NULL map key"),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self.key.take().unwrap_or_default();
+        self.push(key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        Ok((self.names, self.cells))
+    }
+}
+
+impl ser::Serializer for RowSerializer {
+    type Ok = (Vec<String>, Row);
+    type Error = Error;
+    type SerializeSeq = Impossible<Self::Ok, Error>;
+    type SerializeTuple = Impossible<Self::Ok, Error>;
+    type SerializeTupleStruct = Impossible<Self::Ok, Error>;
+    type SerializeTupleVariant = Impossible<Self::Ok, Error>;
+    type SerializeMap = RowCells;
+    type SerializeStruct = RowCells;
+    type SerializeStructVariant = Impossible<Self::Ok, Error>;
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<RowCells, Error> {
+        Ok(RowCells {
+            names: Vec::with_capacity(len),
+            cells: Vec::with_capacity(len),
+            key: None,
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<RowCells, Error> {
+        Ok(RowCells::default())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Error> {
+        value.serialize(self)
+    }
+
+    reject_primitives! {
+        serialize_bool: bool,
+        serialize_i8: i8,
+        serialize_i16: i16,
+        serialize_i32: i32,
+        serialize_i64: i64,
+        serialize_u8: u8,
+        serialize_u16: u16,
+        serialize_u32: u32,
+        serialize_u64: u64,
+        serialize_f32: f32,
+        serialize_f64: f64,
+        serialize_char: char,
+        serialize_str: &str,
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Self::Ok, Error> {
+        unsupported("a bare value")
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Error> {
+        unsupported("a bare value")
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Self::Ok, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Error> {
+        unsupported("a bare value")
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Error> {
+        unsupported("a unit struct")
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+    ) -> Result<Self::Ok, Error> {
+        unsupported("an enum")
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok, Error> {
+        unsupported("an enum")
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        unsupported("a sequence")
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Error> {
+        unsupported("a tuple")
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        unsupported("a tuple struct")
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        unsupported("an enum")
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        unsupported("an enum")
+    }
+}
+
+/// Serializes one field value into a cell (`None` for NULL)
+struct CellSerializer;
+
+impl ser::Serializer for CellSerializer {
+    type Ok = Option<String>;
+    type Error = Error;
+    type SerializeSeq = Impossible<Self::Ok, Error>;
+    type SerializeTuple = Impossible<Self::Ok, Error>;
+    type SerializeTupleStruct = Impossible<Self::Ok, Error>;
+    type SerializeTupleVariant = Impossible<Self::Ok, Error>;
+    type SerializeMap = Impossible<Self::Ok, Error>;
+    type SerializeStruct = Impossible<Self::Ok, Error>;
+    type SerializeStructVariant = Impossible<Self::Ok, Error>;
+
+    format_primitives! {
+        serialize_bool: bool,
+        serialize_i8: i8,
+        serialize_i16: i16,
+        serialize_i32: i32,
+        serialize_i64: i64,
+        serialize_i128: i128,
+        serialize_u8: u8,
+        serialize_u16: u16,
+        serialize_u32: u32,
+        serialize_u64: u64,
+        serialize_u128: u128,
+        serialize_f32: f32,
+        serialize_f64: f64,
+        serialize_char: char,
+        serialize_str: &str,
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Self::Ok, Error> {
+        unsupported("bytes")
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Error> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Self::Ok, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Error> {
+        Ok(None)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Error> {
+        Ok(None)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Error> {
+        Ok(Some(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok, Error> {
+        unsupported("an enum variant with data")
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        unsupported("a sequence")
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Error> {
+        unsupported("a tuple")
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        unsupported("a tuple struct")
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        unsupported("an enum variant with data")
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        unsupported("a nested map")
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Error> {
+        unsupported("a nested struct")
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        unsupported("an enum variant with data")
+    }
+}
+
+/// Presents one row as a map from column name to cell
+struct RowDeserializer<'a> {
+    columns: &'a [String],
+    row: Row,
+}
+
+impl<'de> de::Deserializer<'de> for RowDeserializer<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_map(RowAccess {
+            cells: self.columns.iter().zip(self.row),
+            current: None,
+        })
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+struct RowAccess<'a, I> {
+    cells: I,
+    /// Column and cell whose key was just read
+    current: Option<(&'a String, Option<String>)>,
+}
+
+impl<'de, 'a, I: Iterator<Item = (&'a String, Option<String>)>> MapAccess<'de>
+    for RowAccess<'a, I>
+{
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        let Some((column, cell)) = self.cells.next() else {
+            return Ok(None);
+        };
+        self.current = Some((column, cell));
+        seed.deserialize(column.as_str().into_deserializer())
+            .map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let (column, cell) = self
+            .current
+            .take()
+            .ok_or_else(|| Error("value read before its column".to_string()))?;
+        seed.deserialize(CellDeserializer(cell))
+            .map_err(|err| Error(format!("column {}: {}", column, err)))
+    }
+}
+
+/// Deserializes one cell (`None` for NULL)
+struct CellDeserializer(Option<String>);
+
+impl CellDeserializer {
+    fn value(self) -> Result<String, Error> {
+        self.0
+            .ok_or_else(|| Error("NULL for a field that is not an Option".to_string()))
+    }
+
+    fn parse<T: FromStr>(self) -> Result<T, Error> {
+        let value = self.value()?;
+        value
+            .parse()
+            .map_err(|_| Error(format!("invalid value {:?}", value)))
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                visitor.$visit(self.parse()?)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for CellDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Some(value) => visitor.visit_string(value),
+            None => visitor.visit_none(),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value()?.as_str() {
+            "t" | "true" => visitor.visit_bool(true),
+            "f" | "false" => visitor.visit_bool(false),
+            other => Err(Error(format!("invalid boolean {:?}", other))),
+        }
+    }
+
+    deserialize_parsed! {
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_string(self.0.unwrap_or_default())
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_string(self.0.unwrap_or_default())
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Some(_) => visitor.visit_some(self),
+            None => visitor.visit_none(),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_enum(self.value()?.into_deserializer())
+    }
+
+    serde::forward_to_deserialize_any! {
+        bytes byte_buf unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, PartialEq, serde::Serialize, Deserialize)]
+    struct User {
+        id: u32,
+        name: String,
+        email: Option<String>,
+        active: bool,
+    }
+
+    fn users() -> Vec<User> {
+        vec![
+            User {
+                id: 1,
+                name: "Smith, J.".to_string(),
+                email: Some("j@example.com".to_string()),
+                active: true,
+            },
+            User {
+                id: 2,
+                name: "Bob \"B\"".to_string(),
+                email: None,
+                active: false,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_to_writer() {
+        let mut output = Vec::new();
+        to_writer(&mut output, "users", &users()).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "users[2]{id,name,email,active}:\n1,\"Smith, J.\",j@example.com,true\n2,\"Bob \"\"B\"\"\",,false\n"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut output = Vec::new();
+        to_writer(&mut output, "users", &users()).unwrap();
+        let rows: Vec<User> = from_reader(output.as_slice()).unwrap();
+        assert_eq!(rows, users());
+    }
+
+    #[test]
+    fn test_empty_string_differs_from_none() {
+        #[derive(Debug, PartialEq, serde::Serialize, Deserialize)]
+        struct Note {
+            body: Option<String>,
+        }
+        let notes = vec![
+            Note {
+                body: Some(String::new()),
+            },
+            Note { body: None },
+        ];
+        let mut output = Vec::new();
+        to_writer(&mut output, "notes", &notes).unwrap();
+        assert_eq!(output, b"notes[2]{body}:\n\"\"\n\n");
+        let rows: Vec<Note> = from_reader(output.as_slice()).unwrap();
+        assert_eq!(rows, notes);
+    }
+
+    #[test]
+    fn test_from_reader_psql_values_and_constants() {
+        let input = "users[2]{name,active,id}|email=NULL:\nAlice,t,1\nBob,f,2\n";
+        let rows: Vec<User> = from_reader(input.as_bytes()).unwrap();
+        assert_eq!(rows[0].name, "Alice");
+        assert!(rows[0].active);
+        assert_eq!(rows[1].email, None);
+
+        let maps: Vec<BTreeMap<String, Option<String>>> = from_reader(input.as_bytes()).unwrap();
+        assert_eq!(maps[1]["id"].as_deref(), Some("2"));
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = from_reader::<_, User>("users[1]{id,name,email,active}:\nx,A,,t\n".as_bytes())
+            .unwrap_err();
+        assert_eq!(err.to_string(), "row 1: column id: invalid value \"x\"");
+
+        let err = to_writer(Vec::new(), "user-list", &users()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        let err = to_writer(Vec::new(), "users", &Vec::<User>::new()).unwrap_err();
+        assert_eq!(err.to_string(), "No rows to take the columns of users from");
+
+        let err = to_writer(Vec::new(), "t", &[vec![1, 2]]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "row 1: a sequence cannot be written as a TOSE row"
+        );
+    }
+}
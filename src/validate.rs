@@ -0,0 +1,953 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::dedupe::COUNT_COLUMN;
+use crate::dictionary::Legend;
+use crate::metadata::TOSE_VERSION;
+use crate::nesting::INDENT;
+use crate::profile;
+use crate::reader::{self, Header, Record};
+
+/// Rules checked by [`validate`]: ID, specification section and summary
+pub const RULES: [(&str, &str, &str); 15] = [
+    (
+        "header-grammar",
+        "3.1",
+        "schema header is ENTITY[COUNT]{FIELDS}: with known qualifiers in order",
+    ),
+    (
+        "entity-name",
+        "3.1",
+        "entity names use only letters, digits and _",
+    ),
+    (
+        "field-list",
+        "3.1",
+        "field names are non-empty, with no spaces around commas",
+    ),
+    (
+        "row-count",
+        "3.1",
+        "the data block holds exactly the declared number of records",
+    ),
+    (
+        "field-count",
+        "3.2",
+        "every record has one field per listed field",
+    ),
+    (
+        "csv-escaping",
+        "4",
+        "fields are quoted and escaped as in RFC 4180",
+    ),
+    (
+        "truncation-marker",
+        "3.3",
+        "partial results end with a matching truncation marker",
+    ),
+    (
+        "dictionary",
+        "3.5",
+        "legends follow the header and cover every code",
+    ),
+    (
+        "constants",
+        "3.6",
+        "folded columns are annotated once and not listed in the field list",
+    ),
+    (
+        "dedupe",
+        "3.7",
+        "deduplicated results end with a _count field summing to the original rows",
+    ),
+    (
+        "stats",
+        "3.8",
+        "statistics entities list the statistics fields with valid types and counts",
+    ),
+    (
+        "child-block",
+        "3.9",
+        "every parent record is followed by its indented child block",
+    ),
+    (
+        "document",
+        "3.10",
+        "document header counts and names its entities, each after one empty line",
+    ),
+    (
+        "vertical",
+        "3.11",
+        "vertical blocks list field,value_1..value_N for N records",
+    ),
+    (
+        "metadata",
+        "3.12",
+        "metadata lines read # KEY: VALUE or # tose:1.0",
+    ),
+];
+
+/// Values of the `type` field of a statistics entity
+const STATS_TYPES: [&str; 4] = ["integer", "number", "text", "null"];
+
+/// Order in which row count qualifiers are written
+const QUALIFIERS: [&str; 5] = ["sample=", "deduped from ", "nested=", "vertical", "dict="];
+
+/// A broken rule of the TOSE specification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// Rule ID, one of [`RULES`]
+    pub rule: &'static str,
+    /// 1-based line number
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.line, self.rule, self.message)
+    }
+}
+
+/// Check a TOSE document against the specification, returning every violation
+/// in line order (empty for a conforming document)
+pub fn validate(text: &str) -> Vec<Violation> {
+    let mut lines: Vec<&str> = text.split('\n').collect();
+    if text.ends_with('\n') {
+        lines.pop();
+    }
+    let mut validator = Validator {
+        lines,
+        next: 0,
+        violations: Vec::new(),
+    };
+    validator.document();
+    validator.violations.sort_by_key(|v| v.line);
+    validator.violations
+}
+
+struct Validator<'a> {
+    lines: Vec<&'a str>,
+    /// Index of the next unread line
+    next: usize,
+    violations: Vec<Violation>,
+}
+
+impl<'a> Validator<'a> {
+    fn report(&mut self, rule: &'static str, line: usize, message: impl Into<String>) {
+        self.violations.push(Violation {
+            rule,
+            line,
+            message: message.into(),
+        });
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.lines.get(self.next).copied()
+    }
+
+    /// Take the next line with its 1-based number
+    fn take(&mut self) -> Option<(usize, &'a str)> {
+        let line = self.peek()?;
+        self.next += 1;
+        Some((self.next, line))
+    }
+
+    /// Number of the next line (one past the end at end of input)
+    fn number(&self) -> usize {
+        self.next + 1
+    }
+
+    /// Skip empty lines, returning how many there were
+    fn skip_empty(&mut self) -> usize {
+        let start = self.next;
+        while self.peek() == Some("") {
+            self.next += 1;
+        }
+        self.next - start
+    }
+
+    /// Take the physical lines of one record whose lines start with `indent`
+    fn record_lines(&mut self, indent: &str) -> (usize, Vec<String>) {
+        let first = self.number();
+        let mut lines = Vec::new();
+        while let Some(line) = self.peek() {
+            if !lines.is_empty() && !reader::continues(&lines) {
+                break;
+            }
+            self.next += 1;
+            lines.push(line.strip_prefix(indent).unwrap_or(line).to_string());
+        }
+        (first, lines)
+    }
+
+    /// A whole document: one entity, several separated by empty lines, or a
+    /// multi-entity document
+    fn document(&mut self) {
+        self.metadata();
+        if self.peek().is_some_and(|line| line.starts_with('@')) {
+            self.multi_entity();
+            return;
+        }
+        loop {
+            self.entity();
+            self.skip_empty();
+            if self.peek().is_none() {
+                break;
+            }
+        }
+    }
+
+    /// Check and skip `# ` metadata lines before a header
+    fn metadata(&mut self) {
+        while let Some(line) = self.peek().filter(|line| line.starts_with('#')) {
+            let (number, _) = self.take().unwrap_or_default();
+            if let Some(version) = line.strip_prefix("# tose:") {
+                if version != TOSE_VERSION {
+                    self.report(
+                        "metadata",
+                        number,
+                        format!("unsupported TOSE version {:?}", version),
+                    );
+                }
+                continue;
+            }
+            let valid = line
+                .strip_prefix("# ")
+                .and_then(|entry| entry.split_once(": "))
+                .is_some_and(|(key, _)| {
+                    !key.is_empty()
+                        && key
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+                });
+            if !valid {
+                self.report("metadata", number, "expected # KEY: VALUE");
+            }
+        }
+    }
+
+    /// `@document[N]{NAMES}:`, its preamble, and each entity after one empty line
+    fn multi_entity(&mut self) {
+        let Some((number, line)) = self.take() else {
+            return;
+        };
+        let declared = match line
+            .strip_prefix("@document[")
+            .map(|_| Header::parse(&line[1..]))
+        {
+            Some(Ok(header)) => {
+                if header.fields.len() != header.shown {
+                    self.report(
+                        "document",
+                        number,
+                        format!(
+                            "document header counts {} entities but names {}",
+                            header.shown,
+                            header.fields.len()
+                        ),
+                    );
+                }
+                Some(header.fields)
+            }
+            Some(Err((pos, message))) => {
+                self.report(
+                    "document",
+                    number,
+                    format!("column {}: {}", pos + 2, message),
+                );
+                None
+            }
+            None => {
+                self.report("document", number, "expected @document[N]{ENTITIES}:");
+                None
+            }
+        };
+        while self.peek().is_some_and(|line| line.starts_with("> ")) {
+            self.next += 1;
+        }
+
+        let mut names = Vec::new();
+        loop {
+            let before = self.number();
+            match self.skip_empty() {
+                _ if self.peek().is_none() => break,
+                0 => self.report(
+                    "document",
+                    before,
+                    "expected an empty line before the entity",
+                ),
+                1 => {}
+                _ => self.report(
+                    "document",
+                    before,
+                    "more than one empty line before the entity",
+                ),
+            }
+            self.metadata();
+            let number = self.number();
+            if let Some(name) = self.entity() {
+                names.push((number, name));
+            }
+        }
+
+        let mut seen = HashSet::new();
+        for (number, name) in &names {
+            if !seen.insert(name.clone()) {
+                self.report("document", *number, format!("duplicate entity {}", name));
+            }
+        }
+        if let Some(declared) = declared {
+            let found: Vec<String> = names.into_iter().map(|(_, name)| name).collect();
+            if declared != found {
+                self.report(
+                    "document",
+                    1,
+                    format!(
+                        "document header lists {{{}}} but the document holds {{{}}}",
+                        declared.join(","),
+                        found.join(",")
+                    ),
+                );
+            }
+        }
+    }
+
+    /// One entity: header, legends, data block and truncation marker
+    fn entity(&mut self) -> Option<String> {
+        self.metadata();
+        let Some((number, line)) = self.take() else {
+            self.report("header-grammar", self.number(), "missing schema header");
+            return None;
+        };
+        let Some(header) = self.header(number, line) else {
+            // Without a header the data block cannot be checked
+            while self.peek().is_some_and(|line| !line.is_empty()) {
+                self.next += 1;
+            }
+            return None;
+        };
+
+        let mut legends = Vec::new();
+        for column in &header.dictionary {
+            let Some((line_number, line)) = self.take() else {
+                self.report(
+                    "dictionary",
+                    self.number(),
+                    format!("missing legend for {}", column),
+                );
+                break;
+            };
+            match Legend::parse(column, line) {
+                Ok(legend) => legends.push((line_number, legend)),
+                Err((pos, message)) => self.report(
+                    "dictionary",
+                    line_number,
+                    format!("column {}: {}", pos + 1, message),
+                ),
+            }
+        }
+
+        self.constants(number, &header);
+        let stats = header.entity.ends_with("_stats");
+        if stats && header.fields != profile::FIELDS {
+            self.report(
+                "stats",
+                number,
+                format!(
+                    "statistics entity must list {{{}}}",
+                    profile::FIELDS.join(",")
+                ),
+            );
+        }
+        let deduped = header.deduped_from.is_some();
+        if deduped && !header.vertical && header.fields.last().is_none_or(|f| f != COUNT_COLUMN) {
+            self.report(
+                "dedupe",
+                number,
+                format!("deduplicated result must end with a {} field", COUNT_COLUMN),
+            );
+        }
+
+        let records = if header.vertical {
+            self.vertical(number, &header, &legends)
+        } else {
+            self.table(number, &header, &legends)
+        };
+        if stats && header.fields == profile::FIELDS {
+            for record in &records {
+                self.stats_record(record);
+            }
+        }
+        if deduped {
+            self.counts(number, &header, &records);
+        }
+        self.trailer(&header);
+        Some(header.entity)
+    }
+
+    /// Parse and check a schema header line
+    fn header(&mut self, number: usize, line: &str) -> Option<Header> {
+        let Some(open) = line.find('[') else {
+            self.report("header-grammar", number, "expected ENTITY[COUNT]{FIELDS}:");
+            return None;
+        };
+        if let Err((pos, message)) = reader::check_name(&line[..open], 0) {
+            self.report(
+                "entity-name",
+                number,
+                format!("column {}: {}", pos + 1, message),
+            );
+        }
+        let header = match Header::parse_after_name(line, open) {
+            Ok(header) => header,
+            Err((pos, message)) => {
+                let column = line[..pos.min(line.len())].chars().count() + 1;
+                self.report(
+                    "header-grammar",
+                    number,
+                    format!("column {}: {}", column, message),
+                );
+                return None;
+            }
+        };
+
+        let count = line[open + 1..].split(']').next().unwrap_or_default();
+        let ranks: Vec<usize> = count
+            .split(", ")
+            .skip(1)
+            .filter_map(|q| QUALIFIERS.iter().position(|prefix| q.starts_with(prefix)))
+            .collect();
+        if ranks.windows(2).any(|pair| pair[0] >= pair[1]) {
+            self.report(
+                "header-grammar",
+                number,
+                "qualifiers must be written once each, in the order sample=, deduped from, nested=, vertical, dict=",
+            );
+        }
+
+        for field in &header.fields {
+            if field.is_empty() {
+                self.report("field-list", number, "empty field name");
+            } else if field.trim() != field {
+                self.report(
+                    "field-list",
+                    number,
+                    format!("spaces around field name {:?}", field),
+                );
+            }
+        }
+        Some(header)
+    }
+
+    /// Parse one record, reporting escaping and field count problems
+    fn record(&mut self, indent: &str, expected: usize) -> Option<reader::Record> {
+        let (first, lines) = self.record_lines(indent);
+        match reader::parse_record(&lines, first, indent.len()) {
+            Ok(record) => {
+                if record.fields.len() != expected {
+                    let line = record.positions.get(expected).map_or(record.end.0, |p| p.0);
+                    self.report(
+                        "field-count",
+                        line,
+                        format!(
+                            "expected {} fields, found {}",
+                            expected,
+                            record.fields.len()
+                        ),
+                    );
+                }
+                Some(record)
+            }
+            Err(err) => {
+                self.report(
+                    "csv-escaping",
+                    err.line,
+                    format!("column {}: {}", err.column, err.message),
+                );
+                None
+            }
+        }
+    }
+
+    /// Report codes with no legend entry
+    fn check_codes(
+        &mut self,
+        record: &reader::Record,
+        legend: &Legend,
+        cols: impl Iterator<Item = usize>,
+    ) {
+        for col in cols {
+            if let Some(Some(code)) = record.fields.get(col)
+                && legend.decode(code).is_none()
+            {
+                self.report(
+                    "dictionary",
+                    record.positions[col].0,
+                    format!("unknown code {} for column {}", code, legend.column),
+                );
+            }
+        }
+    }
+
+    /// Whether the next line ends the data block rather than holding a record;
+    /// an empty line is a record of one NULL field while records are expected
+    fn block_ends(&self, empty_is_record: bool) -> bool {
+        match self.peek() {
+            None => true,
+            Some(line) => (line.is_empty() && !empty_is_record) || line.starts_with("# truncated:"),
+        }
+    }
+
+    /// Records of the table layout, each followed by its child block if nested;
+    /// returns the records that parsed
+    fn table(
+        &mut self,
+        number: usize,
+        header: &Header,
+        legends: &[(usize, Legend)],
+    ) -> Vec<Record> {
+        let mut codes = Vec::new();
+        for (line, legend) in legends {
+            match header.fields.iter().position(|f| *f == legend.column) {
+                Some(col) => codes.push((col, legend)),
+                None => self.report(
+                    "dictionary",
+                    *line,
+                    format!("legend for unknown column {}", legend.column),
+                ),
+            }
+        }
+
+        let mut records = Vec::new();
+        let mut child_fields: Option<Vec<String>> = None;
+        for found in 0..header.shown {
+            if self.block_ends(header.fields.len() == 1) {
+                self.report(
+                    "row-count",
+                    number,
+                    format!("header declares {} records, found {}", header.shown, found),
+                );
+                break;
+            }
+            if let Some(record) = self.record("", header.fields.len()) {
+                for (col, legend) in &codes {
+                    self.check_codes(&record, legend, std::iter::once(*col));
+                }
+                records.push(record);
+            }
+            if let Some(entity) = &header.nested {
+                self.child_block(entity, &mut child_fields);
+            }
+        }
+        records
+    }
+
+    /// The indented child block after a parent record
+    fn child_block(&mut self, entity: &str, fields: &mut Option<Vec<String>>) {
+        let Some(line) = self.peek().and_then(|line| line.strip_prefix(INDENT)) else {
+            self.report(
+                "child-block",
+                self.number(),
+                format!("expected the indented {} child block", entity),
+            );
+            return;
+        };
+        let (number, _) = self.take().unwrap_or_default();
+        let child = match Header::parse(line) {
+            Ok(child) => child,
+            Err((pos, message)) => {
+                self.report(
+                    "child-block",
+                    number,
+                    format!("column {}: {}", pos + INDENT.len() + 1, message),
+                );
+                return;
+            }
+        };
+        if child.entity != entity {
+            self.report(
+                "child-block",
+                number,
+                format!("expected child entity {}, found {}", entity, child.entity),
+            );
+        }
+        match fields {
+            Some(fields) if *fields != child.fields => {
+                self.report(
+                    "child-block",
+                    number,
+                    "child fields differ from the first child block",
+                );
+            }
+            Some(_) => {}
+            None => *fields = Some(child.fields.clone()),
+        }
+
+        for found in 0..child.shown {
+            if !self.peek().is_some_and(|line| line.starts_with(INDENT)) {
+                self.report(
+                    "child-block",
+                    number,
+                    format!(
+                        "child header declares {} records, found {}",
+                        child.shown, found
+                    ),
+                );
+                return;
+            }
+            self.record(INDENT, child.fields.len());
+        }
+        if self.peek().is_some_and(|line| line.starts_with(INDENT)) {
+            self.report(
+                "child-block",
+                self.number(),
+                format!("more child records than the {} declared", child.shown),
+            );
+            while self.peek().is_some_and(|line| line.starts_with(INDENT)) {
+                self.next += 1;
+            }
+        }
+    }
+
+    /// One line per column, `name,value_1,...,value_N`; returns the records
+    /// transposed back, as far as they parsed
+    fn vertical(
+        &mut self,
+        number: usize,
+        header: &Header,
+        legends: &[(usize, Legend)],
+    ) -> Vec<Record> {
+        let expected = header.shown + 1;
+        if header.fields.len() != expected {
+            self.report(
+                "vertical",
+                number,
+                format!(
+                    "vertical header for {} records must list {} fields",
+                    header.shown, expected
+                ),
+            );
+        }
+
+        let mut columns = Vec::new();
+        let mut lines = Vec::new();
+        while !self.block_ends(false) {
+            let Some(record) = self.record("", header.fields.len()) else {
+                continue;
+            };
+            let Some(name) = record.fields[0].clone() else {
+                self.report("vertical", record.positions[0].0, "missing column name");
+                continue;
+            };
+            if let Some((_, legend)) = legends.iter().find(|(_, l)| l.column == name) {
+                self.check_codes(&record, legend, 1..record.fields.len());
+            }
+            columns.push(name);
+            lines.push(record);
+        }
+        if header.deduped_from.is_some() && columns.last().is_none_or(|c| c != COUNT_COLUMN) {
+            self.report(
+                "dedupe",
+                number,
+                format!("deduplicated result must end with a {} line", COUNT_COLUMN),
+            );
+        }
+        for (line, legend) in legends {
+            if !columns.contains(&legend.column) {
+                self.report(
+                    "dictionary",
+                    *line,
+                    format!("legend for unknown column {}", legend.column),
+                );
+            }
+        }
+
+        let complete = lines.iter().all(|line| line.fields.len() == expected);
+        if !complete {
+            return Vec::new();
+        }
+        (1..expected)
+            .map(|col| Record {
+                fields: lines.iter().map(|line| line.fields[col].clone()).collect(),
+                positions: lines.iter().map(|line| line.positions[col]).collect(),
+                end: lines.last().map_or((number, 1), |line| line.end),
+            })
+            .collect()
+    }
+
+    /// Folded constant annotations: each column once, none also listed
+    fn constants(&mut self, number: usize, header: &Header) {
+        let fields: &[String] = if header.vertical { &[] } else { &header.fields };
+        let mut seen = HashSet::new();
+        for (column, _) in &header.constants {
+            if fields.contains(column) {
+                self.report(
+                    "constants",
+                    number,
+                    format!("folded column {} is also in the field list", column),
+                );
+            } else if !seen.insert(column) {
+                self.report(
+                    "constants",
+                    number,
+                    format!("column {} is folded twice", column),
+                );
+            }
+        }
+    }
+
+    /// `_count` fields of a deduplicated result and their sum
+    fn counts(&mut self, number: usize, header: &Header, records: &[Record]) {
+        let mut sum = 0;
+        for record in records {
+            let Some(last) = record.fields.len().checked_sub(1) else {
+                continue;
+            };
+            match record.fields[last].as_deref().map(str::parse::<usize>) {
+                Some(Ok(count)) if count > 0 => sum += count,
+                _ => {
+                    self.report(
+                        "dedupe",
+                        record.positions[last].0,
+                        format!("{} must be a positive whole number", COUNT_COLUMN),
+                    );
+                    return;
+                }
+            }
+        }
+        let Some(original) = header.deduped_from else {
+            return;
+        };
+        if records.len() < header.shown {
+            return;
+        }
+        // Only a complete, unsampled result accounts for every original row
+        let exact = !header.is_partial() && header.sample.is_none();
+        if (exact && sum != original) || sum > original {
+            self.report(
+                "dedupe",
+                number,
+                format!(
+                    "{} fields sum to {}, but the header says deduped from {}",
+                    COUNT_COLUMN, sum, original
+                ),
+            );
+        }
+    }
+
+    /// One record of a statistics entity
+    fn stats_record(&mut self, record: &Record) {
+        let field = |name: &str| {
+            let col = profile::FIELDS.iter().position(|f| *f == name).unwrap_or(0);
+            (record.fields[col].as_deref(), record.positions[col].0)
+        };
+        let whole = |value: &str| !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit());
+
+        let (kind, line) = field("type");
+        if !kind.is_some_and(|kind| STATS_TYPES.contains(&kind)) {
+            self.report(
+                "stats",
+                line,
+                format!(
+                    "type must be one of {}, found {:?}",
+                    STATS_TYPES.join(", "),
+                    kind.unwrap_or_default()
+                ),
+            );
+        }
+        let (nulls, line) = field("nulls");
+        if !nulls.is_some_and(whole) {
+            self.report("stats", line, "nulls must be a whole number");
+        }
+        let (distinct, line) = field("distinct");
+        if !distinct.is_some_and(|d| whole(d.strip_suffix('+').unwrap_or(d))) {
+            self.report("stats", line, "distinct must be a whole number, or N+");
+        }
+        let (length, line) = field("length");
+        let valid = length.is_none_or(|length| {
+            length
+                .split_once("..")
+                .is_some_and(|(min, max)| whole(min) && whole(max))
+        });
+        if !valid {
+            self.report("stats", line, "length must read MIN..MAX");
+        }
+    }
+
+    /// Extra records and the truncation marker after the declared records
+    fn trailer(&mut self, header: &Header) {
+        if !header.vertical && !self.block_ends(false) {
+            let number = self.number();
+            let mut extra = 0;
+            while !self.block_ends(false) {
+                self.record_lines("");
+                if header.nested.is_none() || !self.lines[self.next - 1].starts_with(INDENT) {
+                    extra += 1;
+                }
+            }
+            self.report(
+                "row-count",
+                number,
+                format!(
+                    "header declares {} records, found {}",
+                    header.shown,
+                    header.shown + extra
+                ),
+            );
+        }
+
+        let omitted = header.total - header.shown;
+        match self.peek() {
+            Some(line) if line.starts_with("# truncated:") => {
+                let (number, _) = self.take().unwrap_or_default();
+                if omitted == 0 {
+                    self.report(
+                        "truncation-marker",
+                        number,
+                        "truncation marker after a complete result",
+                    );
+                } else if line != format!("# truncated: {} more rows not shown", omitted) {
+                    self.report(
+                        "truncation-marker",
+                        number,
+                        format!("expected \"# truncated: {} more rows not shown\"", omitted),
+                    );
+                }
+            }
+            // A sample may be partial without being cut
+            _ if omitted > 0 && header.sample.is_none() => {
+                self.report(
+                    "truncation-marker",
+                    self.number(),
+                    "partial result without a truncation marker",
+                );
+            }
+            _ => {}
+        }
+
+        if self.peek().is_some_and(|line| !line.is_empty()) {
+            self.report(
+                "row-count",
+                self.number(),
+                "unexpected line after the data block",
+            );
+            while self.peek().is_some_and(|line| !line.is_empty()) {
+                self.next += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(text: &str) -> Vec<(&'static str, usize)> {
+        validate(text)
+            .into_iter()
+            .map(|v| (v.rule, v.line))
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_documents() {
+        assert!(validate("users[2]{id,name}:\n1,Alice\n2,\"Bob, Jr.\"\n").is_empty());
+        assert!(validate("t[1 of 3]{a}:\n1\n# truncated: 2 more rows not shown\n").is_empty());
+        assert!(validate("t[2]{a}:\n\n1\n").is_empty());
+        assert!(
+            validate("# tose:1.0\n@document[2]{a,b}:\n> note\n\na[1]{x}:\n1\n\nb[0]{y}:\n")
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_header_rules() {
+        assert_eq!(rules("my-users[0]{id}:\n"), vec![("entity-name", 1)]);
+        assert_eq!(rules("users[0]{id, name}:\n"), vec![("field-list", 1)]);
+        assert_eq!(rules("users[0]{id}\n"), vec![("header-grammar", 1)]);
+        assert_eq!(
+            rules("t[1, dict=a, vertical]{field,value}:\na={0:x}\na,0\n"),
+            vec![("header-grammar", 1)]
+        );
+    }
+
+    #[test]
+    fn test_record_rules() {
+        assert_eq!(
+            rules("t[3]{a,b}:\n1,2\n3\n"),
+            vec![("row-count", 1), ("field-count", 3)]
+        );
+        assert_eq!(rules("t[1]{a,b}:\n1,2\n3,4\n"), vec![("row-count", 3)]);
+        assert_eq!(rules("t[1]{a}:\nx\"y\n"), vec![("csv-escaping", 2)]);
+        assert_eq!(rules("t[1 of 2]{a}:\n1\n"), vec![("truncation-marker", 3)]);
+    }
+
+    #[test]
+    fn test_constants_rules() {
+        assert!(validate("u[1]{id}|tenant=4,deleted_at=NULL:\n1\n").is_empty());
+        assert_eq!(
+            rules("u[2]{id,tenant}|tenant=4:\n1,4\n2,4\n"),
+            vec![("constants", 1)]
+        );
+        assert_eq!(
+            rules("u[1]{id}|tenant=4,tenant=5:\n1\n"),
+            vec![("constants", 1)]
+        );
+    }
+
+    #[test]
+    fn test_dedupe_rules() {
+        assert!(validate("logs[2, deduped from 3]{level,_count}:\nINFO,2\nWARN,1\n").is_empty());
+        assert!(
+            validate("logs[1 of 2, deduped from 3]{level,_count}:\nINFO,2\n# truncated: 1 more rows not shown\n")
+                .is_empty()
+        );
+        assert!(
+            validate("logs[2, deduped from 3, vertical]{field,value_1,value_2}:\nlevel,INFO,WARN\n_count,2,1\n")
+                .is_empty()
+        );
+        assert_eq!(
+            rules("logs[2, deduped from 99]{level}:\nINFO\nWARN\n"),
+            vec![("dedupe", 1), ("dedupe", 2)]
+        );
+        assert_eq!(
+            rules("logs[2, deduped from 99]{level,_count}:\nINFO,2\nWARN,1\n"),
+            vec![("dedupe", 1)]
+        );
+        assert_eq!(
+            rules("logs[1, deduped from 1]{level,_count}:\nINFO,x\n"),
+            vec![("dedupe", 2)]
+        );
+    }
+
+    #[test]
+    fn test_stats_rules() {
+        let fields = "column,type,nulls,distinct,min,max,mean,top,length";
+        let valid = format!(
+            "t_stats[2]{{{}}}:\nid,integer,0,3,1,3,2,,1..1\nnote,null,3,0,,,,,\n",
+            fields
+        );
+        assert!(validate(&valid).is_empty());
+        assert_eq!(
+            rules("t_stats[1]{column,type}:\nid,integer\n"),
+            vec![("stats", 1)]
+        );
+        assert_eq!(
+            rules(&format!("t_stats[1]{{{}}}:\nid,int,x,3+,,,,,1-2\n", fields)),
+            vec![("stats", 2), ("stats", 2), ("stats", 2)]
+        );
+    }
+
+    #[test]
+    fn test_document_rules() {
+        assert_eq!(
+            rules("@document[2]{a,b}:\n\na[0]{x}:\nb[0]{y}:\n"),
+            vec![("document", 1), ("row-count", 4)]
+        );
+        assert_eq!(
+            rules("@document[1]{a}:\n\na[0]{x}:\n\na[0]{x}:\n"),
+            vec![("document", 1), ("document", 5)]
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+/// Whether records are written as table rows or transposed, one line per column
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RecordLayout {
+    /// One line per record
+    #[default]
+    Table,
+    /// One line per column when the result has few rows or many columns
+    Vertical,
+    /// Whichever of the two layouts takes fewer tokens
+    Auto,
+}
+
+impl FromStr for RecordLayout {
+    type Err = io::Error;
+
+    /// Parse `table`, `vertical` or `auto`
+    fn from_str(s: &str) -> io::Result<Self> {
+        match s {
+            "table" => Ok(RecordLayout::Table),
+            "vertical" => Ok(RecordLayout::Vertical),
+            "auto" => Ok(RecordLayout::Auto),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Unknown layout: {} (expected table, vertical or auto)", s),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for RecordLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordLayout::Table => write!(f, "table"),
+            RecordLayout::Vertical => write!(f, "vertical"),
+            RecordLayout::Auto => write!(f, "auto"),
+        }
+    }
+}
+
+/// Field list of a transposed block holding `records` records:
+/// `field,value` for one record, `field,value_1,...,value_N` otherwise
+pub(crate) fn fields(records: usize) -> Vec<String> {
+    let mut fields = vec!["field".to_string()];
+    if records == 1 {
+        fields.push("value".to_string());
+    } else {
+        fields.extend((1..=records).map(|n| format!("value_{}", n)));
+    }
+    fields
+}
+
+/// Transpose rows into one line per column, led by the column name
+pub(crate) fn transpose(columns: &[String], rows: &[Vec<String>]) -> Vec<Vec<String>> {
+    columns
+        .iter()
+        .enumerate()
+        .map(|(col, name)| {
+            let mut line = vec![name.clone()];
+            line.extend(rows.iter().map(|row| row[col].clone()));
+            line
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transpose() {
+        let columns = vec!["id".to_string(), "name".to_string()];
+        let rows = vec![
+            vec!["1".to_string(), "Alice".to_string()],
+            vec!["2".to_string(), String::new()],
+        ];
+        assert_eq!(
+            transpose(&columns, &rows),
+            vec![vec!["id", "1", "2"], vec!["name", "Alice", ""]]
+        );
+        assert_eq!(fields(2), vec!["field", "value_1", "value_2"]);
+        assert_eq!(fields(1), vec!["field", "value"]);
+    }
+
+    #[test]
+    fn test_parse_layout() {
+        assert_eq!("auto".parse::<RecordLayout>().unwrap(), RecordLayout::Auto);
+        assert!("wide".parse::<RecordLayout>().is_err());
+    }
+}
@@ -0,0 +1,165 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+const FENCE: &str = "```tose\n{body}\n```\n";
+
+const XML: &str = "<data name=\"{entity}\" rows=\"{rows}\">\n{body}\n</data>\n";
+
+const EXPLAIN: &str = "\
+The data below is in TOSE format. The first line is a header \
+`entity[rows]{field1,field2,...}:` naming the entity, its row count and its \
+fields; every following line is one record in CSV (RFC 4180) with the fields \
+in header order. An empty field is NULL. `N of TOTAL` in the row count means \
+only N of TOTAL rows are included.
+Query: {query}
+
+```tose
+{body}
+```
+";
+
+/// Text written around a converted document, e.g. a markdown fence
+///
+/// Placeholders: `{entity}`, `{rows}` (rows in the document), `{total}` (rows
+/// in the full result), `{columns}` (comma-separated), `{query}` and `{body}`
+/// (the TOSE output without its final newline). Lines containing `{query}`
+/// are left out when no source query is known. Other text, including
+/// unknown `{...}`, is copied as is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WrapTemplate {
+    template: String,
+}
+
+impl WrapTemplate {
+    /// Template from its text
+    pub fn new(template: impl Into<String>) -> Self {
+        WrapTemplate {
+            template: template.into(),
+        }
+    }
+
+    /// Read a template file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::new(fs::read_to_string(path)?))
+    }
+
+    /// Fill in the placeholders
+    pub(crate) fn render(&self, context: &WrapContext, body: &str) -> String {
+        let query = context.query.as_deref();
+        let body = body.strip_suffix('\n').unwrap_or(body);
+        let value = |name: &str| -> Option<String> {
+            match name {
+                "entity" => Some(context.entity.clone()),
+                "rows" => Some(context.rows.to_string()),
+                "total" => Some(context.total.to_string()),
+                "columns" => Some(context.columns.join(",")),
+                "query" => Some(query.unwrap_or_default().to_string()),
+                "body" => Some(body.to_string()),
+                _ => None,
+            }
+        };
+
+        let mut rendered = String::new();
+        for line in self.template.split_inclusive('\n') {
+            if query.is_none() && line.contains("{query}") {
+                continue;
+            }
+            // Single pass, so substituted text is never expanded again
+            let mut rest = line;
+            while let Some(start) = rest.find('{') {
+                rendered.push_str(&rest[..start]);
+                rest = &rest[start..];
+                match rest
+                    .find('}')
+                    .and_then(|end| Some((end, value(&rest[1..end])?)))
+                {
+                    Some((end, value)) => {
+                        rendered.push_str(&value);
+                        rest = &rest[end + 1..];
+                    }
+                    None => {
+                        rendered.push('{');
+                        rest = &rest[1..];
+                    }
+                }
+            }
+            rendered.push_str(rest);
+        }
+        rendered
+    }
+}
+
+impl FromStr for WrapTemplate {
+    type Err = io::Error;
+
+    /// Built-in `fence`, `xml` or `explain`, otherwise a template file path
+    fn from_str(s: &str) -> io::Result<Self> {
+        match s {
+            "fence" => Ok(Self::new(FENCE)),
+            "xml" => Ok(Self::new(XML)),
+            "explain" => Ok(Self::new(EXPLAIN)),
+            path => Self::from_file(path).map_err(|err| {
+                io::Error::new(
+                    err.kind(),
+                    format!("Cannot read wrap template {}: {}", path, err),
+                )
+            }),
+        }
+    }
+}
+
+/// What a template may refer to, gathered while converting
+#[derive(Debug, Clone, Default)]
+pub(crate) struct WrapContext {
+    pub(crate) entity: String,
+    pub(crate) rows: usize,
+    pub(crate) total: usize,
+    pub(crate) columns: Vec<String>,
+    pub(crate) query: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> WrapContext {
+        WrapContext {
+            entity: "users".to_string(),
+            rows: 2,
+            total: 5,
+            columns: vec!["id".to_string(), "name".to_string()],
+            query: None,
+        }
+    }
+
+    #[test]
+    fn test_placeholders() {
+        let template = WrapTemplate::new("{entity} {rows}/{total} ({columns}) {other}\n{body}\n");
+        assert_eq!(
+            template.render(&context(), "a\nb\n"),
+            "users 2/5 (id,name) {other}\na\nb\n"
+        );
+    }
+
+    #[test]
+    fn test_query_lines_dropped_without_query() {
+        let template = WrapTemplate::new("Query: {query}\n{body}\n");
+        assert_eq!(template.render(&context(), "x\n"), "x\n");
+        let context = WrapContext {
+            query: Some("SELECT 1".to_string()),
+            ..context()
+        };
+        assert_eq!(template.render(&context, "x\n"), "Query: SELECT 1\nx\n");
+    }
+
+    #[test]
+    fn test_body_is_not_expanded() {
+        let template = WrapTemplate::from_str("fence").unwrap();
+        assert_eq!(
+            template.render(&context(), "note\n{entity}\n"),
+            "```tose\nnote\n{entity}\n```\n"
+        );
+    }
+}
@@ -0,0 +1,240 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, Write};
+
+use crate::{PsqlTable, check_column_name, check_entity_name};
+
+/// Bytes of rows kept in memory before an unknown-count writer spools them
+/// to a temporary file
+const SPOOL_THRESHOLD: usize = 1 << 20;
+
+/// Writes one TOSE entity row by row, for producing TOSE without psql input
+///
+/// [`with_row_count`](Self::with_row_count) writes the schema header at once
+/// and streams each row straight through. [`new`](Self::new) does not know the
+/// count up front, so rows are held back (in memory, then in a temporary file
+/// once they outgrow 1 MiB) until [`finish`](Self::finish) writes the header.
+/// Entity and column names are limited to letters, digits and `_`. `None`
+/// cells are written as `NULL`; empty strings as `""`, so they read back as
+/// empty rather than `NULL`.
+pub struct ToseWriter<W: Write> {
+    output: W,
+    entity: String,
+    fields: Vec<String>,
+    declared: Option<usize>,
+    rows: usize,
+    /// Rows held back until the count is known
+    spool: Option<Spool>,
+}
+
+impl<W: Write> ToseWriter<W> {
+    /// Writer for an entity whose row count is known only once every row has
+    /// been written
+    pub fn new(output: W, entity: &str, columns: &[&str]) -> io::Result<Self> {
+        check_entity_name(entity)?;
+        if columns.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "An entity needs at least one column",
+            ));
+        }
+        for column in columns {
+            check_column_name(column)?;
+        }
+        Ok(ToseWriter {
+            output,
+            entity: entity.to_string(),
+            fields: columns.iter().map(|c| c.to_string()).collect(),
+            declared: None,
+            rows: 0,
+            spool: Some(Spool::Memory(Vec::new())),
+        })
+    }
+
+    /// Writer for an entity of exactly `rows` rows; the header is written
+    /// immediately
+    pub fn with_row_count(
+        output: W,
+        entity: &str,
+        columns: &[&str],
+        rows: usize,
+    ) -> io::Result<Self> {
+        let mut writer = Self::new(output, entity, columns)?;
+        writer.spool = None;
+        writer.declared = Some(rows);
+        writer.write_header(rows)?;
+        Ok(writer)
+    }
+
+    /// Write one row, with a cell per column
+    pub fn write_row(&mut self, row: &[Option<&str>]) -> io::Result<()> {
+        if row.len() != self.fields.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Expected {} fields, got {}", self.fields.len(), row.len()),
+            ));
+        }
+        if self.declared.is_some_and(|declared| self.rows == declared) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("More rows than the {} declared", self.rows),
+            ));
+        }
+
+        let cells: Vec<String> = row.iter().map(|cell| field(*cell)).collect();
+        let line = cells.join(",") + "\n";
+        match &mut self.spool {
+            Some(spool) => spool.write(line.as_bytes())?,
+            None => self.output.write_all(line.as_bytes())?,
+        }
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// Rows written so far
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Complete the entity, writing the header and held-back rows of an
+    /// unknown-count writer, and return the output
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(declared) = self.declared
+            && self.rows != declared
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Declared {} rows, wrote {}", declared, self.rows),
+            ));
+        }
+        if let Some(spool) = self.spool.take() {
+            self.write_header(self.rows)?;
+            spool.copy_to(&mut self.output)?;
+        }
+        self.output.flush()?;
+        Ok(self.output)
+    }
+
+    fn write_header(&mut self, rows: usize) -> io::Result<()> {
+        writeln!(
+            self.output,
+            "{}[{}]{{{}}}:",
+            self.entity,
+            rows,
+            self.fields.join(",")
+        )
+    }
+}
+
+/// One field of a record: empty for `NULL`, `""` for an empty string
+pub(crate) fn field(cell: Option<&str>) -> String {
+    match cell {
+        Some("") => "\"\"".to_string(),
+        Some(value) => PsqlTable::escape_csv_field(value),
+        None => String::new(),
+    }
+}
+
+/// Rows of an unknown-count writer
+enum Spool {
+    Memory(Vec<u8>),
+    File(BufWriter<File>),
+}
+
+impl Spool {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        if let Spool::Memory(buffer) = self
+            && buffer.len() + bytes.len() > SPOOL_THRESHOLD
+        {
+            let mut file = BufWriter::new(tempfile::tempfile()?);
+            file.write_all(buffer)?;
+            *self = Spool::File(file);
+        }
+        match self {
+            Spool::Memory(buffer) => buffer.extend_from_slice(bytes),
+            Spool::File(file) => file.write_all(bytes)?,
+        }
+        Ok(())
+    }
+
+    fn copy_to<W: Write>(self, output: &mut W) -> io::Result<()> {
+        match self {
+            Spool::Memory(buffer) => output.write_all(&buffer),
+            Spool::File(file) => {
+                let mut file = file.into_inner().map_err(|err| err.into_error())?;
+                file.rewind()?;
+                io::copy(&mut file, output).map(drop)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_count_streams_rows() {
+        let mut writer =
+            ToseWriter::with_row_count(Vec::new(), "users", &["id", "name"], 2).unwrap();
+        writer.write_row(&[Some("1"), Some("Smith, J.")]).unwrap();
+        writer.write_row(&[Some("2"), None]).unwrap();
+        assert!(writer.write_row(&[Some("3"), None]).is_err());
+        let output = writer.finish().unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "users[2]{id,name}:\n1,\"Smith, J.\"\n2,\n"
+        );
+
+        let writer = ToseWriter::with_row_count(Vec::new(), "users", &["id"], 2).unwrap();
+        let err = writer.finish().unwrap_err();
+        assert_eq!(err.to_string(), "Declared 2 rows, wrote 0");
+    }
+
+    #[test]
+    fn test_unknown_count_writes_header_last() {
+        let mut writer = ToseWriter::new(Vec::new(), "notes", &["id", "body"]).unwrap();
+        writer.write_row(&[Some("1"), Some("")]).unwrap();
+        writer.write_row(&[Some("2"), Some("say \"hi\"")]).unwrap();
+        let err = writer.write_row(&[Some("3")]).unwrap_err();
+        assert_eq!(err.to_string(), "Expected 2 fields, got 1");
+        let output = writer.finish().unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "notes[2]{id,body}:\n1,\"\"\n2,\"say \"\"hi\"\"\"\n"
+        );
+    }
+
+    #[test]
+    fn test_invalid_column_names() {
+        for column in ["a,b", "x}", ""] {
+            let err = ToseWriter::new(Vec::new(), "t", &["id", column])
+                .err()
+                .unwrap();
+            assert_eq!(
+                err.to_string(),
+                format!(
+                    "Invalid column name: {:?} (use letters, digits and _)",
+                    column
+                )
+            );
+        }
+
+        let err = ToseWriter::new(Vec::new(), "t", &[]).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(err.to_string(), "An entity needs at least one column");
+        assert!(ToseWriter::with_row_count(Vec::new(), "t", &[], 0).is_err());
+    }
+
+    #[test]
+    fn test_large_output_spools_to_file() {
+        let value = "x".repeat(1000);
+        let mut writer = ToseWriter::new(Vec::new(), "blobs", &["body"]).unwrap();
+        for _ in 0..2000 {
+            writer.write_row(&[Some(&value)]).unwrap();
+        }
+        assert!(matches!(writer.spool, Some(Spool::File(_))));
+        let output = String::from_utf8(writer.finish().unwrap()).unwrap();
+        assert!(output.starts_with("blobs[2000]{body}:\nxxx"));
+        assert_eq!(output.lines().count(), 2001);
+    }
+}
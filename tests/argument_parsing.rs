@@ -1,10 +1,10 @@
-use assert_cmd::Command;
+use assert_cmd::cargo::cargo_bin_cmd;
 use predicates::prelude::*;
 
 #[test]
 fn test_no_arguments_required() {
     // The tool should work with no arguments, reading psql table from stdin
-    let mut cmd = Command::cargo_bin("tose_converter").unwrap();
+    let mut cmd = cargo_bin_cmd!("tose_converter");
     let input = "  id  |  name  \n------+--------\n   1  | Alice  \n   2  | Bob    \n(2 rows)\n";
     cmd.write_stdin(input)
         .assert()
@@ -15,7 +15,7 @@ fn test_no_arguments_required() {
 #[test]
 fn test_arguments_ignored() {
     // Even if arguments are provided, they should be ignored (for backward compat testing)
-    let mut cmd = Command::cargo_bin("tose_converter").unwrap();
+    let mut cmd = cargo_bin_cmd!("tose_converter");
     let input = "  id  |  name  \n------+--------\n   1  | Alice  \n(1 row)\n";
     // Providing args that would have been used in old version
     cmd.arg("users")
@@ -30,7 +30,7 @@ fn test_arguments_ignored() {
 
 #[test]
 fn test_empty_input_fails_gracefully() {
-    let mut cmd = Command::cargo_bin("tose_converter").unwrap();
+    let mut cmd = cargo_bin_cmd!("tose_converter");
     cmd.write_stdin("")
         .assert()
         .failure()
@@ -40,9 +40,32 @@ fn test_empty_input_fails_gracefully() {
 #[test]
 fn test_non_psql_input_fails() {
     // CSV input should fail since we only accept psql format now
-    let mut cmd = Command::cargo_bin("tose_converter").unwrap();
+    let mut cmd = cargo_bin_cmd!("tose_converter");
     cmd.write_stdin("id,name\n1,Alice\n2,Bob\n")
         .assert()
         .failure()
         .stderr(predicate::str::contains("no separator line found"));
 }
+
+#[test]
+fn test_max_rows_flag() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    let input = "  id  |  name  \n------+--------\n   1  | Alice  \n   2  | Bob    \n(2 rows)\n";
+    cmd.arg("--max-rows")
+        .arg("1")
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(predicate::eq("result[1 of 2]{id,name}:\n1,Alice\n# truncated: 1 more rows not shown\n"));
+}
+
+#[test]
+fn test_invalid_limit_value_fails() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.arg("--max-tokens")
+        .arg("lots")
+        .write_stdin("  id  \n------\n   1  \n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid value for --max-tokens: lots"));
+}
@@ -0,0 +1,103 @@
+#![cfg(feature = "tokio")]
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tokio::io::{AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tose_converter::ToseConverter;
+
+const PSQL: &str = "SELECT id, name FROM users;\n id |   name\n----+-----------\n  1 | Smith, J.\n  2 |\n(2 rows)\n\nTime: 0.412 ms\n";
+
+#[tokio::test]
+async fn test_convert_async_matches_blocking_convert() {
+    let converter = ToseConverter::new()
+        .with_entity_name("users")
+        .with_metadata(true);
+
+    let mut expected = Vec::new();
+    converter.convert(PSQL.as_bytes(), &mut expected).unwrap();
+
+    let mut output = Vec::new();
+    converter
+        .convert_async(BufReader::new(PSQL.as_bytes()), &mut output)
+        .await
+        .unwrap();
+    assert_eq!(output, expected);
+    assert!(
+        String::from_utf8(output)
+            .unwrap()
+            .ends_with("users[2]{id,name}:\n1,\"Smith, J.\"\n2,\n")
+    );
+}
+
+#[tokio::test]
+async fn test_convert_async_reports_parse_errors() {
+    let mut output = Vec::new();
+    let err = ToseConverter::new()
+        .convert_async(BufReader::new("not a table\n".as_bytes()), &mut output)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    assert!(output.is_empty());
+}
+
+#[tokio::test]
+async fn test_convert_async_streams_through_small_pipes() {
+    let mut psql = String::from(" id\n----\n");
+    for id in 0..10_000 {
+        psql.push_str(&format!(" {}\n", id));
+    }
+    psql.push_str("(10000 rows)\n");
+
+    let (mut psql_in, psql_out) = tokio::io::duplex(64);
+    let (tose_in, mut tose_out) = tokio::io::duplex(64);
+    let feed = async {
+        psql_in.write_all(psql.as_bytes()).await.unwrap();
+        drop(psql_in);
+    };
+    let convert = async {
+        ToseConverter::new()
+            .convert_async(BufReader::new(psql_out), tose_in)
+            .await
+            .unwrap();
+    };
+    let mut tose = String::new();
+    let read = tose_out.read_to_string(&mut tose);
+    let ((), (), read) = tokio::join!(feed, convert, read);
+    read.unwrap();
+    assert!(tose.starts_with("result[10000]{id}:\n0\n1\n"));
+    assert!(tose.ends_with("9999\n"));
+}
+
+/// Output whose every write fails
+struct FailingWriter;
+
+impl AsyncWrite for FailingWriter {
+    fn poll_write(self: Pin<&mut Self>, _: &mut Context<'_>, _: &[u8]) -> Poll<io::Result<usize>> {
+        Poll::Ready(Err(io::Error::other("disk full")))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[tokio::test]
+async fn test_convert_async_reports_write_errors() {
+    let mut psql = String::from(" id\n----\n");
+    for id in 0..200_000 {
+        psql.push_str(&format!(" {}\n", id));
+    }
+    psql.push_str("(200000 rows)\n");
+
+    let err = ToseConverter::new()
+        .convert_async(BufReader::new(psql.as_bytes()), FailingWriter)
+        .await
+        .unwrap_err();
+    assert_eq!(err.to_string(), "disk full");
+}
@@ -0,0 +1,79 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use tose_converter::ToseConverter;
+
+fn convert(converter: ToseConverter, input: &str) -> String {
+    let mut output = Vec::new();
+    converter.convert(input.as_bytes(), &mut output).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn test_long_cells_are_truncated() {
+    let long_value = "x".repeat(50_000);
+    let input = format!("  id  |  data       \n------+-------------\n   1  | {}  \n   2  | short       \n", long_value);
+
+    let result = convert(ToseConverter::new().with_max_cell_chars(10), &input);
+    assert_eq!(result, "result[2]{id,data}:\n1,xxxxxxxxxx…[+49990 chars]\n2,short\n");
+}
+
+#[test]
+fn test_truncation_happens_before_escaping() {
+    // The cut lands inside the quoted region; the output must still be valid CSV
+    let input = "  id  |  text                  \n------+------------------------\n   1  | Say \"hello\", then go  \n";
+
+    let result = convert(ToseConverter::new().with_max_cell_chars(5), input);
+    assert_eq!(result, "result[1]{id,text}:\n1,\"Say \"\"…[+15 chars]\"\n");
+}
+
+#[test]
+fn test_cells_the_note_would_lengthen_are_kept() {
+    let input = "  id  |  text        \n------+--------------\n   1  | abcdefghijk  \n";
+
+    let result = convert(ToseConverter::new().with_max_cell_chars(10), input);
+    assert_eq!(result, "result[1]{id,text}:\n1,abcdefghijk\n");
+}
+
+#[test]
+fn test_column_override() {
+    let input = "  title   |  body                      \n----------+----------------------------\n abcdefgh | abcdefghijklmnopqrstuvwxyz \n";
+
+    let converter = ToseConverter::new()
+        .with_max_cell_chars(100)
+        .with_column_max_chars("body", 4);
+    let result = convert(converter, input);
+    assert_eq!(result, "result[1]{title,body}:\nabcdefgh,abcd…[+22 chars]\n");
+}
+
+#[test]
+fn test_blob_summaries() {
+    let input = "  id  |  payload    \n------+-------------\n   1  | \\x61        \n   2  | not a blob  \n";
+
+    let result = convert(ToseConverter::new().with_blob_summaries(true), input);
+    assert_eq!(
+        result,
+        "result[2]{id,payload}:\n1,\\x[1 bytes fnv1a64=af63dc4c8601ec8c]\n2,not a blob\n"
+    );
+}
+
+#[test]
+fn test_max_cell_chars_flags() {
+    let input = "  title               |  body                      \n----------------------+----------------------------\n abcdefghijklmnopqrst | abcdefghijklmnopqrstuvwxyz \n";
+
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--max-cell-chars", "3", "--max-cell-chars", "body=5"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(predicate::eq("result[1]{title,body}:\nabc…[+17 chars],abcde…[+21 chars]\n"));
+}
+
+#[test]
+fn test_override_for_unknown_column_fails() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--max-cell-chars", "nope=5"])
+        .write_stdin("  id  \n------\n   1  \n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Unknown column for cell limit: nope"));
+}
@@ -0,0 +1,58 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use tose_converter::{ColumnSelector, ToseConverter};
+
+const INPUT: &str = "  id  |  name   |  email             |  created_at  \n------+---------+--------------------+--------------\n   1  | Alice   | alice@example.com  | 2025-01-01   \n   2  | Bob     | bob@example.com    | 2025-01-02   \n(2 rows)\n";
+
+fn convert(converter: ToseConverter) -> String {
+    let mut output = Vec::new();
+    converter.convert(INPUT.as_bytes(), &mut output).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn test_select_and_reorder_columns() {
+    let converter = ToseConverter::new().with_columns(ColumnSelector::parse_list("name,id").unwrap());
+    assert_eq!(convert(converter), "result[2]{name,id}:\nAlice,1\nBob,2\n");
+}
+
+#[test]
+fn test_exclude_and_rename() {
+    let converter = ToseConverter::new()
+        .with_excluded_columns(ColumnSelector::parse_list("email").unwrap())
+        .with_rename("created_at", "created");
+    assert_eq!(
+        convert(converter),
+        "result[2]{id,name,created}:\n1,Alice,2025-01-01\n2,Bob,2025-01-02\n"
+    );
+}
+
+#[test]
+fn test_columns_flags() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--columns", "id,/^(email|name)$/", "--exclude", "e*", "--rename", "id=user_id"])
+        .write_stdin(INPUT)
+        .assert()
+        .success()
+        .stdout(predicate::eq("result[2]{user_id,name}:\n1,Alice\n2,Bob\n"));
+}
+
+#[test]
+fn test_unknown_column_fails() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--columns", "id,phone"])
+        .write_stdin(INPUT)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Unknown column: phone"));
+}
+
+#[test]
+fn test_invalid_rename_fails() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--rename", "created_at"])
+        .write_stdin(INPUT)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("expected old=new"));
+}
@@ -0,0 +1,54 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use tose_converter::{DedupeOrder, ToseConverter};
+
+const INPUT: &str = " id | level |  message   \n----+-------+------------\n  1 | INFO  | started\n  2 | WARN  | slow query\n  3 | WARN  | slow query\n  4 | INFO  | started\n  5 | WARN  | slow query\n  6 | ERROR | \n(6 rows)\n";
+
+#[test]
+fn test_dedupe_first_seen() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--dedupe", "--exclude", "id"])
+        .write_stdin(INPUT)
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "result[3, deduped from 6]{level,message,_count}:\nINFO,started,2\nWARN,slow query,3\nERROR,,1\n",
+        ));
+}
+
+#[test]
+fn test_dedupe_by_frequency() {
+    let converter = ToseConverter::new()
+        .with_excluded_columns(vec!["id".parse().unwrap()])
+        .with_dedupe(DedupeOrder::Frequency);
+    let mut output = Vec::new();
+    converter.convert(INPUT.as_bytes(), &mut output).unwrap();
+
+    let result = String::from_utf8(output).unwrap();
+    assert_eq!(
+        result,
+        "result[3, deduped from 6]{level,message,_count}:\nWARN,slow query,3\nINFO,started,2\nERROR,,1\n"
+    );
+}
+
+#[test]
+fn test_dedupe_with_max_rows() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--dedupe-order", "frequency", "--exclude", "id", "--max-rows", "1"])
+        .write_stdin(INPUT)
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "result[1 of 3, deduped from 6]{level,message,_count}:\nWARN,slow query,3\n# truncated: 2 more rows not shown\n",
+        ));
+}
+
+#[test]
+fn test_unknown_dedupe_order() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--dedupe-order", "random"])
+        .write_stdin(INPUT)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Unknown dedupe order: random"));
+}
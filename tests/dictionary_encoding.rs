@@ -0,0 +1,64 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use tose_converter::ToseConverter;
+
+fn orders(rows: usize) -> String {
+    let statuses = ["pending", "shipped", "shipped", "delivered"];
+    let mut input = "  id  |  status    |  country        \n------+------------+-----------------\n".to_string();
+    for i in 0..rows {
+        input.push_str(&format!("   {}  | {}  | United Kingdom  \n", i + 1, statuses[i % 4]));
+    }
+    input.push_str(&format!("({} rows)\n", rows));
+    input
+}
+
+#[test]
+fn test_low_cardinality_columns_are_encoded() {
+    let converter = ToseConverter::new().with_dictionary_encoding(8);
+    let mut output = Vec::new();
+    converter.convert(orders(8).as_bytes(), &mut output).unwrap();
+
+    let result = String::from_utf8(output).unwrap();
+    assert_eq!(
+        result,
+        "result[8, dict=status+country]{id,status,country}:\n\
+         status={0:shipped,1:pending,2:delivered}\n\
+         country={0:United Kingdom}\n\
+         1,1,0\n2,0,0\n3,0,0\n4,2,0\n5,1,0\n6,0,0\n7,0,0\n8,2,0\n"
+    );
+}
+
+#[test]
+fn test_encoding_shrinks_output() {
+    let input = orders(200);
+    let mut plain = Vec::new();
+    ToseConverter::new().convert(input.as_bytes(), &mut plain).unwrap();
+    let mut encoded = Vec::new();
+    ToseConverter::new()
+        .with_dictionary_encoding(8)
+        .convert(input.as_bytes(), &mut encoded)
+        .unwrap();
+
+    assert!(encoded.len() * 2 < plain.len());
+}
+
+#[test]
+fn test_high_cardinality_columns_left_alone() {
+    let converter = ToseConverter::new().with_dictionary_encoding(2);
+    let mut output = Vec::new();
+    converter.convert(orders(8).as_bytes(), &mut output).unwrap();
+
+    let result = String::from_utf8(output).unwrap();
+    assert!(result.starts_with("result[8, dict=country]{id,status,country}:\ncountry={0:United Kingdom}\n1,pending,0\n"));
+}
+
+#[test]
+fn test_truncated_encoded_result() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--dict", "--max-rows", "1"])
+        .write_stdin(orders(8))
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("result[1 of 8, dict=status+country]{id,status,country}:\n"))
+        .stdout(predicate::str::ends_with("1,1,0\n# truncated: 7 more rows not shown\n"));
+}
@@ -0,0 +1,80 @@
+use std::fs;
+use std::path::Path;
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use tose_converter::validate;
+
+fn write(dir: &Path, name: &str, contents: &str) -> String {
+    let path = dir.join(name);
+    fs::write(&path, contents).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+#[test]
+fn test_diff_snapshots() {
+    let dir = tempfile::tempdir().unwrap();
+    let before = write(
+        dir.path(),
+        "before.tose",
+        "users[3]{id,name,plan}:\n1,\"Smith, J.\",free\n2,Bob,pro\n3,Carol,free\n",
+    );
+    let after = write(
+        dir.path(),
+        "after.tose",
+        "users[3]{id,plan,name}:\n1,pro,\"Smith, J.\"\n3,free,Carol\n4,free,Dave\n",
+    );
+
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    let assert = cmd
+        .args(["diff", &before, &after, "--key", "id"])
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "@document[4]{schema,added,removed,changed}:\n\n\
+             schema[2]{change,column,before,after}:\nmoved,name,2,3\nmoved,plan,3,2\n\n\
+             added[1]{id,plan,name}:\n4,free,Dave\n\n\
+             removed[1]{id,name,plan}:\n2,Bob,pro\n\n\
+             changed[1]{id,column,old,new}:\n1,plan,free,pro\n",
+        ));
+    let output = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+    assert!(validate(&output).is_empty());
+}
+
+#[test]
+fn test_diff_composite_key() {
+    let dir = tempfile::tempdir().unwrap();
+    let before = write(
+        dir.path(),
+        "before.tose",
+        "stock[2]{store,sku,qty}:\n1,A,5\n2,A,7\n",
+    );
+    let after = write(
+        dir.path(),
+        "after.tose",
+        "stock[2]{store,sku,qty}:\n1,A,4\n2,A,7\n",
+    );
+
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["diff", &before, &after, "--key", "store,sku"])
+        .assert()
+        .success()
+        .stdout(predicate::str::ends_with(
+            "changed[1]{store,sku,column,old,new}:\n1,A,qty,5,4\n",
+        ));
+}
+
+#[test]
+fn test_diff_usage_errors() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["diff", "a.tose", "b.tose"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("diff requires --key COLUMN"));
+
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["diff", "a.tose", "--key", "id"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("diff requires two files"));
+}
@@ -0,0 +1,114 @@
+use std::fs;
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use tose_converter::{ProfileMode, ToseConverter};
+
+const USERS: &str = " id | name  \n----+-------\n  1 | Alice\n  2 | Bob\n(2 rows)\n";
+const ORDERS: &str = " id | total \n----+-------\n 10 |  9.99\n(1 row)\n";
+
+#[test]
+fn test_document_from_named_inputs() {
+    let converter = ToseConverter::new().with_preamble("Users and their orders.\nTotals in EUR.");
+    let mut output = Vec::new();
+    converter
+        .convert_document(
+            vec![
+                ("users".to_string(), USERS.as_bytes()),
+                ("orders".to_string(), ORDERS.as_bytes()),
+            ],
+            &mut output,
+        )
+        .unwrap();
+
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "@document[2]{users,orders}:\n> Users and their orders.\n> Totals in EUR.\n\nusers[2]{id,name}:\n1,Alice\n2,Bob\n\norders[1]{id,total}:\n10,9.99\n"
+    );
+}
+
+#[test]
+fn test_document_lists_stats_entities() {
+    let converter = ToseConverter::new().with_profile(ProfileMode::Only);
+    let mut output = Vec::new();
+    converter
+        .convert_document(vec![("users".to_string(), USERS.as_bytes())], &mut output)
+        .unwrap();
+
+    let result = String::from_utf8(output).unwrap();
+    assert!(result.starts_with("@document[1]{users_stats}:\n\nusers_stats[2]{"));
+}
+
+#[test]
+fn test_duplicate_entity_names_rejected() {
+    let err = ToseConverter::new()
+        .convert_document(
+            vec![
+                ("users".to_string(), USERS.as_bytes()),
+                ("users".to_string(), USERS.as_bytes()),
+            ],
+            Vec::new(),
+        )
+        .unwrap_err();
+    assert!(err.to_string().contains("Duplicate entity name in document: users"));
+}
+
+#[test]
+fn test_multi_result_stdin() {
+    let input = format!("{}\n{}\nTime: 0.412 ms\n", USERS, ORDERS);
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--multi", "--entity", "users"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "@document[2]{users,result2}:\n\nusers[2]{id,name}:\n1,Alice\n2,Bob\n\nresult2[1]{id,total}:\n10,9.99\n",
+        ));
+}
+
+#[test]
+fn test_input_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let users = dir.path().join("users.txt");
+    let orders = dir.path().join("orders.txt");
+    fs::write(&users, USERS).unwrap();
+    fs::write(&orders, ORDERS).unwrap();
+
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.arg("--input")
+        .arg(format!("users={}", users.display()))
+        .arg("--input")
+        .arg(format!("orders={}", orders.display()))
+        .args(["--preamble", "Two tables"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "@document[2]{users,orders}:\n> Two tables\n\nusers[2]{id,name}:\n",
+        ));
+}
+
+#[test]
+fn test_entity_name() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--entity", "users"])
+        .write_stdin(USERS)
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("users[2]{id,name}:\n"));
+
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--entity", "bad name"])
+        .write_stdin(USERS)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid entity name"));
+}
+
+#[test]
+fn test_missing_input_file() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--input", "users=/nonexistent/users.txt"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("/nonexistent/users.txt"));
+}
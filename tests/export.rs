@@ -0,0 +1,50 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+
+const DOCUMENT: &str = "@document[2]{users,orders}:\n\nusers[2]{id,name}:\n1,O'Brien\n2,\n\norders[1]{id,user_id}:\n10,1\n";
+
+#[test]
+fn test_export_inserts_every_entity() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["export", "--to", "sql"])
+        .write_stdin(DOCUMENT)
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "INSERT INTO users (id, name) VALUES\n('1', 'O''Brien'),\n('2', NULL);\nINSERT INTO orders (id, user_id) VALUES\n('10', '1');\n",
+        ));
+}
+
+#[test]
+fn test_export_copy_with_quoted_identifiers() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["export", "--to", "sql", "--copy", "--quote-identifiers"])
+        .write_stdin("users[2 of 5]{id,name}:\n1,Alice\n2,\n# truncated: 3 more rows not shown\n")
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "-- users: 2 of 5 rows\nCOPY \"users\" (\"id\", \"name\") FROM STDIN;\n1\tAlice\n2\t\\N\n\\.\n",
+        ));
+}
+
+#[test]
+fn test_export_requires_sql_format() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["export", "--to", "json"])
+        .write_stdin(DOCUMENT)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Unknown export format: json"));
+}
+
+#[test]
+fn test_export_reports_malformed_input() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["export", "--to", "sql"])
+        .write_stdin("users[2]{id,name}:\n1,Alice\n")
+        .assert()
+        .failure()
+        .stderr(predicate::eq(
+            "Error: line 3, column 1: header declares 2 records, found 1\n",
+        ));
+}
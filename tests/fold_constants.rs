@@ -0,0 +1,39 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use tose_converter::ToseConverter;
+
+const INPUT: &str = "  id  | tenant_id |  name  | deleted_at | note     \n------+-----------+--------+------------+----------\n   1  |        42 | Alice  |            | a, b     \n   2  |        42 | Bob    |            | a, b     \n(2 rows)\n";
+
+#[test]
+fn test_constant_columns_move_to_header() {
+    let converter = ToseConverter::new().with_fold_constants(true);
+    let mut output = Vec::new();
+    converter.convert(INPUT.as_bytes(), &mut output).unwrap();
+
+    let result = String::from_utf8(output).unwrap();
+    assert_eq!(
+        result,
+        "result[2]{id,name}|tenant_id=42,deleted_at=NULL,note=\"a, b\":\n1,Alice\n2,Bob\n"
+    );
+}
+
+#[test]
+fn test_folding_disabled_by_default() {
+    let mut output = Vec::new();
+    ToseConverter::new().convert(INPUT.as_bytes(), &mut output).unwrap();
+
+    let result = String::from_utf8(output).unwrap();
+    assert!(result.starts_with("result[2]{id,tenant_id,name,deleted_at,note}:\n"));
+}
+
+#[test]
+fn test_fold_constants_with_truncation() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--fold-constants", "--max-rows", "1", "--exclude", "note"])
+        .write_stdin(INPUT)
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "result[1 of 2]{id,name}|tenant_id=42,deleted_at=NULL:\n1,Alice\n# truncated: 1 more rows not shown\n",
+        ));
+}
@@ -1,9 +1,9 @@
-use assert_cmd::Command;
+use assert_cmd::cargo::cargo_bin_cmd;
 use predicates::prelude::*;
 
 /// Test helper to run the tose_converter binary with psql table input
 fn run_converter(input: &str) -> assert_cmd::assert::Assert {
-    let mut cmd = Command::cargo_bin("tose_converter").unwrap();
+    let mut cmd = cargo_bin_cmd!("tose_converter");
     cmd.write_stdin(input).assert()
 }
 
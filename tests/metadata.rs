@@ -0,0 +1,106 @@
+use std::time::{Duration, UNIX_EPOCH};
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use tose_converter::ToseConverter;
+
+/// psql output with `-e` echo and `\timing` enabled
+const ECHOED: &str = "Timing is on.\nSELECT id, name\n  FROM users;\n id | name  \n----+-------\n  1 | Alice\n  2 | Bob\n(2 rows)\n\nTime: 0.412 ms\n";
+
+const INPUT: &str = " id | name  \n----+-------\n  1 | Alice\n  2 | Bob\n(2 rows)\n";
+
+#[test]
+fn test_metadata_from_echoed_query_and_timing() {
+    let converter = ToseConverter::new()
+        .with_metadata(true)
+        .with_source("analytics replica")
+        .with_generated_at(UNIX_EPOCH + Duration::from_secs(1_772_366_400));
+    let mut output = Vec::new();
+    converter.convert(ECHOED.as_bytes(), &mut output).unwrap();
+
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "# tose:1.0\n# source: analytics replica\n# generated: 2026-03-01T12:00:00Z\n# query: SELECT id, name FROM users;\n# time: 0.412 ms\nresult[2]{id,name}:\n1,Alice\n2,Bob\n"
+    );
+}
+
+#[test]
+fn test_command_tags_are_not_taken_as_the_query() {
+    // `psql -f` without `-e`: tags of the earlier statements, then the table
+    let input = format!("SET\nBEGIN\nINSERT 0 1\n{}", INPUT);
+    let mut output = Vec::new();
+    ToseConverter::new()
+        .with_metadata(true)
+        .with_generated_at(UNIX_EPOCH)
+        .convert(input.as_bytes(), &mut output)
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "# tose:1.0\n# generated: 1970-01-01T00:00:00Z\nresult[2]{id,name}:\n1,Alice\n2,Bob\n"
+    );
+}
+
+#[test]
+fn test_timing_of_a_later_query_is_not_taken() {
+    let input = format!("{}\nSELECT 2;\n{}\nTime: 9.9 ms\n", INPUT, INPUT);
+    let mut output = Vec::new();
+    ToseConverter::new()
+        .with_metadata(true)
+        .with_generated_at(UNIX_EPOCH)
+        .convert(input.as_bytes(), &mut output)
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "# tose:1.0\n# generated: 1970-01-01T00:00:00Z\nresult[2]{id,name}:\n1,Alice\n2,Bob\n"
+    );
+}
+
+#[test]
+fn test_metadata_truncated_and_query_flag() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--meta", "--query", "SELECT * FROM users", "--max-rows", "1"])
+        .write_stdin(INPUT)
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(
+            "^# tose:1.0\n# generated: \\d{4}-\\d{2}-\\d{2}T\\d{2}:\\d{2}:\\d{2}Z\n# query: SELECT \\* FROM users\n# truncated: 1 of 2 rows\nresult\\[1 of 2\\]\\{id,name\\}:\n1,Alice\n# truncated: 1 more rows not shown\n$",
+        ).unwrap());
+}
+
+#[test]
+fn test_source_implies_meta() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--source", "prod"])
+        .write_stdin(INPUT)
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "# tose:1.0\n# source: prod\n# generated: ",
+        ));
+}
+
+#[test]
+fn test_metadata_in_document() {
+    let input = "SELECT 1 AS a;\n a \n---\n 1\n(1 row)\n\nTime: 1.0 ms\nSELECT 2 AS b;\n b \n---\n 2\n(1 row)\n\nTime: 2.0 ms\n";
+    let converter = ToseConverter::new()
+        .with_metadata(true)
+        .with_generated_at(UNIX_EPOCH);
+    let mut output = Vec::new();
+    converter
+        .convert_results(input.as_bytes(), &[], &mut output)
+        .unwrap();
+
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "# tose:1.0\n# generated: 1970-01-01T00:00:00Z\n@document[2]{result1,result2}:\n\n# query: SELECT 1 AS a;\n# time: 1.0 ms\nresult1[1]{a}:\n1\n\n# query: SELECT 2 AS b;\n# time: 2.0 ms\nresult2[1]{b}:\n2\n"
+    );
+}
+
+#[test]
+fn test_no_metadata_by_default() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.write_stdin(ECHOED)
+        .assert()
+        .success()
+        .stdout(predicate::eq("result[2]{id,name}:\n1,Alice\n2,Bob\n"));
+}
@@ -0,0 +1,126 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use tose_converter::ToseConverter;
+
+const INPUT: &str = " order_id | customer | sku | qty \n----------+----------+-----+-----\n     1001 | Alice    | A1  |   2\n     1001 | Alice    | B7  |   1\n     1002 | Bob      |     |    \n     1003 | Carol    | C3  |   5\n(4 rows)\n";
+
+#[test]
+fn test_group_by_nests_children() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--group-by", "order_id", "--parent-columns", "customer"])
+        .write_stdin(INPUT)
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "result[3, nested=items]{order_id,customer}:\n1001,Alice\n  items[2]{sku,qty}:\n  A1,2\n  B7,1\n1002,Bob\n  items[0]{sku,qty}:\n1003,Carol\n  items[1]{sku,qty}:\n  C3,5\n",
+        ));
+}
+
+#[test]
+fn test_child_entity_and_max_rows() {
+    let converter = ToseConverter::new()
+        .with_group_by(vec!["order_id".to_string()], vec!["customer".to_string()])
+        .with_child_entity("lines")
+        .with_max_rows(1);
+    let mut output = Vec::new();
+    converter.convert(INPUT.as_bytes(), &mut output).unwrap();
+
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "result[1 of 3, nested=lines]{order_id,customer}:\n1001,Alice\n  lines[2]{sku,qty}:\n  A1,2\n  B7,1\n# truncated: 2 more rows not shown\n"
+    );
+}
+
+#[test]
+fn test_group_by_key_only() {
+    let converter = ToseConverter::new()
+        .with_group_by(vec!["order_id".to_string()], Vec::new())
+        .with_filter("order_id = 1003".parse().unwrap());
+    let mut output = Vec::new();
+    converter.convert(INPUT.as_bytes(), &mut output).unwrap();
+
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "result[1, nested=items]{order_id}:\n1003\n  items[1]{customer,sku,qty}:\n  Carol,C3,5\n"
+    );
+}
+
+#[test]
+fn test_parent_columns_must_be_consistent() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--group-by", "customer", "--parent-columns", "order_id"])
+        .write_stdin(INPUT.replace("1003 | Carol", "1003 | Alice"))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Parent columns differ within group customer=Alice"));
+}
+
+#[test]
+fn test_parent_columns_require_group_by() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--parent-columns", "customer"])
+        .write_stdin(INPUT)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("require --group-by"));
+}
+
+#[test]
+fn test_group_by_uses_source_names() {
+    let converter = ToseConverter::new()
+        .with_group_by(vec!["order_id".to_string()], vec!["customer".to_string()])
+        .with_rename("order_id", "id")
+        .with_rename("customer", "name")
+        .with_filter("order_id = 1003".parse().unwrap());
+    let mut output = Vec::new();
+    converter.convert(INPUT.as_bytes(), &mut output).unwrap();
+
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "result[1, nested=items]{id,name}:\n1003,Carol\n  items[1]{sku,qty}:\n  C3,5\n"
+    );
+}
+
+#[test]
+fn test_dictionary_encoding_rejected_with_group_by() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--group-by", "order_id", "--dict"])
+        .write_stdin(INPUT)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--dict cannot be combined with --group-by"));
+
+    let err = ToseConverter::new()
+        .with_group_by(vec!["order_id".to_string()], Vec::new())
+        .with_dictionary_encoding(8)
+        .convert(INPUT.as_bytes(), Vec::new())
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Dictionary encoding cannot be combined with --group-by"
+    );
+}
+
+#[test]
+fn test_invalid_child_entity_rejected() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--group-by", "order_id", "--child-entity", "line items"])
+        .write_stdin(INPUT)
+        .assert()
+        .failure()
+        .stdout(predicate::str::is_empty())
+        .stderr(
+            predicate::str::contains("Invalid entity name")
+                .and(predicate::str::contains("line items")),
+        );
+
+    let err = ToseConverter::new()
+        .with_group_by(vec!["order_id".to_string()], Vec::new())
+        .with_child_entity("line items")
+        .convert(INPUT.as_bytes(), Vec::new())
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Invalid entity name: \"line items\" (use letters, digits and _)"
+    );
+}
@@ -0,0 +1,41 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use tose_converter::{ProfileMode, ToseConverter};
+
+const INPUT: &str = " id | status  | total \n----+---------+-------\n  1 | shipped |  9.99\n  2 | pending |  14.5\n  3 | shipped |      \n(3 rows)\n";
+
+const STATS: &str = "result_stats[3]{column,type,nulls,distinct,min,max,mean,top,length}:\nid,integer,0,3,1,3,2,,1..1\nstatus,text,0,2,pending,shipped,,shipped=2,7..7\ntotal,number,1,2,9.99,14.5,12.25,,4..4\n";
+
+#[test]
+fn test_profile_after_rows() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.arg("--profile")
+        .write_stdin(INPUT)
+        .assert()
+        .success()
+        .stdout(predicate::eq(format!(
+            "result[3]{{id,status,total}}:\n1,shipped,9.99\n2,pending,14.5\n3,shipped,\n\n{}",
+            STATS
+        )));
+}
+
+#[test]
+fn test_profile_only() {
+    let converter = ToseConverter::new().with_profile(ProfileMode::Only);
+    let mut output = Vec::new();
+    converter.convert(INPUT.as_bytes(), &mut output).unwrap();
+
+    assert_eq!(String::from_utf8(output).unwrap(), STATS);
+}
+
+#[test]
+fn test_profile_covers_all_rows_and_follows_projection() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--profile", "--max-rows", "1", "--columns", "status", "--rename", "status=state"])
+        .write_stdin(INPUT)
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "result[1 of 3]{state}:\nshipped\n# truncated: 2 more rows not shown\n\nresult_stats[1]{column,type,nulls,distinct,min,max,mean,top,length}:\nstate,text,0,2,pending,shipped,,shipped=2,7..7\n",
+        ));
+}
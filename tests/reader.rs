@@ -0,0 +1,103 @@
+use std::io;
+
+use tose_converter::{ParseError, Row, ToseConverter, ToseReader};
+
+const INPUT: &str = " id |      status       |   note   \n----+-------------------+----------\n  1 | awaiting_shipment | a, b\n  2 | pending           | \n  3 | awaiting_shipment | \n  4 | awaiting_shipment | say \"hi\"\n(4 rows)\n";
+
+fn convert(converter: ToseConverter) -> String {
+    let mut output = Vec::new();
+    converter.convert(INPUT.as_bytes(), &mut output).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+fn rows(reader: ToseReader<&[u8]>) -> Vec<Row> {
+    reader.collect::<io::Result<_>>().unwrap()
+}
+
+fn cells(values: &[&str]) -> Row {
+    values
+        .iter()
+        .map(|v| (!v.is_empty()).then(|| v.to_string()))
+        .collect()
+}
+
+fn expected() -> Vec<Row> {
+    vec![
+        cells(&["1", "awaiting_shipment", "a, b"]),
+        cells(&["2", "pending", ""]),
+        cells(&["3", "awaiting_shipment", ""]),
+        cells(&["4", "awaiting_shipment", "say \"hi\""]),
+    ]
+}
+
+#[test]
+fn test_read_converted_document() {
+    let document = convert(ToseConverter::new().with_entity_name("orders"));
+    let reader = ToseReader::new(document.as_bytes()).unwrap();
+    assert_eq!(reader.header().entity, "orders");
+    assert_eq!(reader.columns(), ["id", "status", "note"]);
+    assert_eq!(rows(reader), expected());
+}
+
+#[test]
+fn test_read_dictionary_and_vertical() {
+    let document = convert(ToseConverter::new().with_dictionary_encoding(8));
+    assert!(document.contains("dict=status"));
+    assert_eq!(
+        rows(ToseReader::new(document.as_bytes()).unwrap()),
+        expected()
+    );
+
+    let document = convert(
+        ToseConverter::new()
+            .with_layout("vertical".parse().unwrap())
+            .with_vertical_max_rows(4)
+            .with_dictionary_encoding(8),
+    );
+    let reader = ToseReader::new(document.as_bytes()).unwrap();
+    assert!(reader.header().vertical);
+    assert_eq!(reader.columns(), ["id", "status", "note"]);
+    assert_eq!(rows(reader), expected());
+}
+
+#[test]
+fn test_read_truncated_with_metadata() {
+    let document = convert(
+        ToseConverter::new()
+            .with_max_rows(2)
+            .with_metadata(true)
+            .with_source_query("SELECT * FROM orders"),
+    );
+    let reader = ToseReader::new(document.as_bytes()).unwrap();
+    assert_eq!((reader.header().shown, reader.header().total), (2, 4));
+    assert!(
+        reader
+            .metadata()
+            .contains(&("query".to_string(), "SELECT * FROM orders".to_string()))
+    );
+    assert_eq!(rows(reader), expected()[..2]);
+}
+
+#[test]
+fn test_read_nested_and_multi_entity() {
+    let document = "@document[2]{orders,users}:\n> preamble\n\norders[2, nested=items]{order_id}:\n1001\n  items[2]{sku,note}:\n  A1,\"two\n  lines\"\n  B7,\n1002\n  items[0]{sku,note}:\n\nusers[1]{id}:\n7\n";
+    let reader = ToseReader::new(document.as_bytes()).unwrap();
+    assert_eq!(reader.header().nested.as_deref(), Some("items"));
+    let mut reader = reader.next_entity().unwrap().unwrap();
+    assert_eq!(reader.header().entity, "users");
+    assert_eq!(reader.next().unwrap().unwrap(), cells(&["7"]));
+    assert!(reader.next_entity().unwrap().is_none());
+}
+
+#[test]
+fn test_malformed_input_reports_position() {
+    let err = ToseReader::new("users[1]{id,name}\n1,Alice\n".as_bytes())
+        .err()
+        .unwrap();
+    let parse = err.get_ref().unwrap().downcast_ref::<ParseError>().unwrap();
+    assert_eq!((parse.line, parse.column), (1, 18));
+    assert_eq!(
+        err.to_string(),
+        "line 1, column 18: expected ':' at the end of the header"
+    );
+}
@@ -0,0 +1,48 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use tose_converter::{ToseConverter, render_psql};
+
+const DOCUMENT: &str =
+    "users[3]{id,name,email}:\n1,\"Smith, J.\",j@example.com\n2,Bob,\n10,Carol,c@example.com\n";
+
+#[test]
+fn test_render_psql_table() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.arg("render")
+        .write_stdin(DOCUMENT)
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            " id |   name    |     email     \n----+-----------+---------------\n  1 | Smith, J. | j@example.com\n  2 | Bob       |\n 10 | Carol     | c@example.com\n(3 rows)\n",
+        ));
+}
+
+#[test]
+fn test_rendered_table_converts_back() {
+    let mut table = Vec::new();
+    render_psql(DOCUMENT.as_bytes(), &mut table).unwrap();
+
+    let mut document = Vec::new();
+    ToseConverter::new()
+        .with_entity_name("users")
+        .convert(table.as_slice(), &mut document)
+        .unwrap();
+    assert_eq!(String::from_utf8(document).unwrap(), DOCUMENT);
+}
+
+#[test]
+fn test_render_check() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["render", "--check"])
+        .write_stdin(DOCUMENT)
+        .assert()
+        .success();
+
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["render", "--check"])
+        .write_stdin("notes[1]{id,body}:\n1,\"two\nlines\"\n")
+        .assert()
+        .failure()
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::contains("Round trip failed for notes"));
+}
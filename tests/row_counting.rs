@@ -63,3 +63,61 @@ fn test_count_large_result() {
     let result = String::from_utf8(output).unwrap();
     assert!(result.starts_with("result[1000]{id,value}:\n"));
 }
+
+#[test]
+fn test_max_rows_reports_partial_count() {
+    let converter = ToseConverter::new().with_max_rows(2);
+    let input = b"  id  |  name  \n------+--------\n   1  | Alice  \n   2  | Bob    \n   3  | Charlie\n(3 rows)\n";
+    let mut output = Vec::new();
+
+    converter.convert(&input[..], &mut output).unwrap();
+
+    let result = String::from_utf8(output).unwrap();
+    assert_eq!(
+        result,
+        "result[2 of 3]{id,name}:\n1,Alice\n2,Bob\n# truncated: 1 more rows not shown\n"
+    );
+}
+
+#[test]
+fn test_max_rows_not_reached() {
+    let converter = ToseConverter::new().with_max_rows(10);
+    let input = b"  id  |  name  \n------+--------\n   1  | Alice  \n   2  | Bob    \n(2 rows)\n";
+    let mut output = Vec::new();
+
+    converter.convert(&input[..], &mut output).unwrap();
+
+    let result = String::from_utf8(output).unwrap();
+    assert_eq!(result, "result[2]{id,name}:\n1,Alice\n2,Bob\n");
+}
+
+#[test]
+fn test_max_tokens_stops_within_budget() {
+    let converter = ToseConverter::new().with_max_tokens(60);
+    let mut input = "  id  |  value  \n------+---------\n".to_string();
+    for i in 1..=1000 {
+        input.push_str(&format!("   {}  | data    \n", i));
+    }
+    let mut output = Vec::new();
+
+    converter.convert(input.as_bytes(), &mut output).unwrap();
+
+    let result = String::from_utf8(output).unwrap();
+    let shown = result.lines().count() - 2;
+    assert!(shown > 0);
+    assert!(result.starts_with(&format!("result[{} of 1000]{{id,value}}:\n", shown)));
+    assert!(result.ends_with(&format!("# truncated: {} more rows not shown\n", 1000 - shown)));
+    assert!(result.chars().count().div_ceil(4) <= 60);
+}
+
+#[test]
+fn test_max_tokens_not_reached() {
+    let converter = ToseConverter::new().with_max_tokens(1000);
+    let input = b"  id  |  name  \n------+--------\n   1  | Alice  \n(1 row)\n";
+    let mut output = Vec::new();
+
+    converter.convert(&input[..], &mut output).unwrap();
+
+    let result = String::from_utf8(output).unwrap();
+    assert_eq!(result, "result[1]{id,name}:\n1,Alice\n");
+}
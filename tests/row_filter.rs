@@ -0,0 +1,63 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use tose_converter::{RowFilter, ToseConverter};
+
+const INPUT: &str = "  id  |  status  |  retries  |  error          \n------+----------+-----------+-----------------\n   1  | ok       |         0 |                 \n   2  | failed   |         5 | timeout         \n   3  | failed   |         2 | refused         \n   4  | failed   |        12 |                 \n(4 rows)\n";
+
+fn convert(filter: &str) -> String {
+    let converter = ToseConverter::new().with_filter(filter.parse::<RowFilter>().unwrap());
+    let mut output = Vec::new();
+    converter.convert(INPUT.as_bytes(), &mut output).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn test_filter_updates_row_count() {
+    assert_eq!(
+        convert(r#"status == "failed" && retries > 3"#),
+        "result[2]{id,status,retries,error}:\n2,failed,5,timeout\n4,failed,12,\n"
+    );
+}
+
+#[test]
+fn test_filter_is_null_and_like() {
+    assert_eq!(
+        convert("error IS NULL AND status LIKE 'fail%'"),
+        "result[1]{id,status,retries,error}:\n4,failed,12,\n"
+    );
+}
+
+#[test]
+fn test_filter_matching_nothing() {
+    assert_eq!(convert("retries > 100"), "result[0]{id,status,retries,error}:\n");
+}
+
+#[test]
+fn test_filter_on_excluded_column() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--where", "status = 'failed' OR retries IS NULL", "--exclude", "status,error"])
+        .write_stdin(INPUT)
+        .assert()
+        .success()
+        .stdout(predicate::eq("result[3]{id,retries}:\n2,5\n3,2\n4,12\n"));
+}
+
+#[test]
+fn test_invalid_expression_fails() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--where", "status =="])
+        .write_stdin(INPUT)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid --where expression"));
+}
+
+#[test]
+fn test_unknown_column_fails() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--where", "attempts > 1"])
+        .write_stdin(INPUT)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Unknown column in --where expression: attempts"));
+}
@@ -0,0 +1,94 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use tose_converter::{SampleMethod, ToseConverter};
+
+fn psql_input(rows: usize) -> String {
+    let mut input = "  id  |  status  \n------+----------\n".to_string();
+    for i in 1..=rows {
+        let status = if i % 10 == 0 { "failed" } else { "ok" };
+        input.push_str(&format!("   {}  | {}  \n", i, status));
+    }
+    input.push_str(&format!("({} rows)\n", rows));
+    input
+}
+
+fn convert(converter: ToseConverter, input: &str) -> String {
+    let mut output = Vec::new();
+    converter.convert(input.as_bytes(), &mut output).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn test_head_tail_sample_header() {
+    let converter = ToseConverter::new().with_sample(SampleMethod::HeadTail, 4);
+    let result = convert(converter, &psql_input(10));
+    assert_eq!(
+        result,
+        "result[4 of 10, sample=head+tail]{id,status}:\n1,ok\n2,ok\n9,ok\n10,failed\n"
+    );
+}
+
+#[test]
+fn test_uniform_sample_is_seeded() {
+    let input = psql_input(1000);
+    let a = convert(ToseConverter::new().with_sample(SampleMethod::Uniform { seed: 9 }, 10), &input);
+    let b = convert(ToseConverter::new().with_sample(SampleMethod::Uniform { seed: 9 }, 10), &input);
+    let c = convert(ToseConverter::new().with_sample(SampleMethod::Uniform { seed: 10 }, 10), &input);
+
+    assert_eq!(a, b);
+    assert_ne!(a, c);
+    assert!(a.starts_with("result[10 of 1000, sample=uniform(seed=9)]{id,status}:\n"));
+    assert_eq!(a.lines().count(), 11);
+}
+
+#[test]
+fn test_sample_larger_than_result_is_not_a_sample() {
+    let converter = ToseConverter::new().with_sample(SampleMethod::Reservoir { seed: 1 }, 50);
+    let result = convert(converter, &psql_input(3));
+    assert_eq!(result, "result[3]{id,status}:\n1,ok\n2,ok\n3,ok\n");
+}
+
+#[test]
+fn test_sample_then_truncate_keeps_marker() {
+    let converter = ToseConverter::new()
+        .with_sample(SampleMethod::Tail, 5)
+        .with_max_rows(2);
+    let result = convert(converter, &psql_input(10));
+    assert_eq!(
+        result,
+        "result[2 of 10, sample=tail]{id,status}:\n6,ok\n7,ok\n# truncated: 8 more rows not shown\n"
+    );
+}
+
+#[test]
+fn test_stratified_sample_flag() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--sample", "stratified:status", "--sample-size", "10", "--seed", "3"])
+        .write_stdin(psql_input(100))
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "result[10 of 100, sample=stratified(status,seed=3)]{id,status}:\n",
+        ))
+        .stdout(predicate::str::contains(",failed\n"));
+}
+
+#[test]
+fn test_stratified_unknown_column_fails_on_small_results() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--sample", "stratified:nope", "--sample-size", "10"])
+        .write_stdin(psql_input(3))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Unknown column for stratified sample: nope"));
+}
+
+#[test]
+fn test_unknown_sample_method_fails() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--sample", "random"])
+        .write_stdin(psql_input(3))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Unknown sample method: random"));
+}
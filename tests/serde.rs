@@ -0,0 +1,36 @@
+#![cfg(feature = "serde")]
+
+use serde::{Deserialize, Serialize};
+use tose_converter::{ToseConverter, from_reader, to_writer, validate};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Order {
+    id: i64,
+    customer: String,
+    total: f64,
+    note: Option<String>,
+}
+
+#[test]
+fn test_structs_read_from_converted_psql_output() {
+    let psql = " id | customer  | total | note\n----+-----------+-------+------\n  1 | Smith, J. | 9.5   |\n  2 | Bob       | 12    | rush\n(2 rows)\n";
+    let mut document = Vec::new();
+    ToseConverter::new()
+        .with_entity_name("orders")
+        .convert(psql.as_bytes(), &mut document)
+        .unwrap();
+
+    let orders: Vec<Order> = from_reader(document.as_slice()).unwrap();
+    assert_eq!(orders[0].customer, "Smith, J.");
+    assert_eq!(orders[0].note, None);
+    assert_eq!(orders[1].total, 12.0);
+
+    let mut written = Vec::new();
+    to_writer(&mut written, "orders", &orders).unwrap();
+    let written = String::from_utf8(written).unwrap();
+    assert!(validate(&written).is_empty());
+    assert_eq!(
+        written,
+        "orders[2]{id,customer,total,note}:\n1,\"Smith, J.\",9.5,\n2,Bob,12,rush\n"
+    );
+}
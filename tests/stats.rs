@@ -0,0 +1,83 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use std::io::Write;
+use tose_converter::{TokenCounter, ToseConverter};
+
+const INPUT: &str = "  id  |  name   |  email\n------+---------+------------------\n   1  | Alice   | alice@example.com\n   2  | Bob     | bob@example.com\n(2 rows)\n";
+
+#[test]
+fn test_convert_with_stats_output_unchanged() {
+    let converter = ToseConverter::new();
+    let mut output = Vec::new();
+
+    let stats = converter
+        .convert_with_stats(INPUT.as_bytes(), &mut output, &TokenCounter::heuristic())
+        .unwrap();
+
+    let result = String::from_utf8(output).unwrap();
+    assert_eq!(result, "result[2]{id,name,email}:\n1,Alice,alice@example.com\n2,Bob,bob@example.com\n");
+    assert!(!stats.bpe);
+
+    let tose = stats.get("tose").unwrap();
+    assert_eq!(tose.bytes, result.len());
+    assert_eq!(tose.rows, 2);
+    assert_eq!(tose.tokens, result.chars().count().div_ceil(4));
+    assert_eq!(stats.get("psql").unwrap().bytes, INPUT.len());
+}
+
+#[test]
+fn test_tose_smaller_than_json() {
+    let converter = ToseConverter::new();
+    let stats = converter
+        .convert_with_stats(INPUT.as_bytes(), Vec::new(), &TokenCounter::heuristic())
+        .unwrap();
+
+    let tose = stats.get("tose").unwrap();
+    let json = stats.get("json").unwrap();
+    assert!(tose.bytes < json.bytes);
+    assert!(tose.tokens < json.tokens);
+}
+
+#[test]
+fn test_stats_flag_reports_to_stderr() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.arg("--stats")
+        .env_remove("TOSE_VOCAB")
+        .write_stdin(INPUT)
+        .assert()
+        .success()
+        .stdout(predicate::eq("result[2]{id,name,email}:\n1,Alice,alice@example.com\n2,Bob,bob@example.com\n"))
+        .stderr(predicate::str::contains("estimated (chars/4)"))
+        .stderr(predicate::str::contains("jsonl"));
+}
+
+#[test]
+fn test_stats_with_vocab_file() {
+    // Single-byte vocabulary: every byte is one token
+    let mut vocab = tempfile::NamedTempFile::new().unwrap();
+    let alphabet = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    for byte in 0u8..=255 {
+        let first = alphabet[(byte >> 2) as usize] as char;
+        let second = alphabet[((byte & 3) << 4) as usize] as char;
+        writeln!(vocab, "{}{}== {}", first, second, byte).unwrap();
+    }
+
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.arg("--stats")
+        .arg("--vocab")
+        .arg(vocab.path())
+        .write_stdin(INPUT)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("tokens: bpe"));
+}
+
+#[test]
+fn test_unknown_option_fails() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.arg("--bogus")
+        .write_stdin(INPUT)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Unknown option: --bogus"));
+}
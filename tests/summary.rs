@@ -0,0 +1,79 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use tose_converter::ToseConverter;
+
+fn input(rows: usize) -> String {
+    let mut input = String::from(" id | kind \n----+------\n");
+    for i in 1..=rows {
+        let kind = if i % 3 == 0 { "view" } else { "click" };
+        input.push_str(&format!(" {} | {}\n", i, kind));
+    }
+    input.push_str(&format!("({} rows)\n", rows));
+    input
+}
+
+#[test]
+fn test_summary_reports_real_count() {
+    let converter = ToseConverter::new().with_summary(3, 42);
+    let mut output = Vec::new();
+    converter.convert(input(1000).as_bytes(), &mut output).unwrap();
+
+    let result = String::from_utf8(output).unwrap();
+    let lines: Vec<&str> = result.lines().collect();
+    assert_eq!(lines[0], "result[3 of 1000, sample=reservoir(seed=42)]{id,kind}:");
+    assert_eq!(lines[4], "");
+    assert_eq!(
+        &lines[5..],
+        &[
+            "result_stats[2]{column,type,nulls,distinct,min,max,mean,top,length}:",
+            "id,integer,0,1000,1,1000,500.5,,1..4",
+            "kind,text,0,2,click,view,,click=667;view=333,4..5",
+        ]
+    );
+
+    // Sampled rows keep their input order
+    let ids: Vec<usize> = lines[1..4]
+        .iter()
+        .map(|line| line.split(',').next().unwrap().parse().unwrap())
+        .collect();
+    assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[test]
+fn test_summary_of_small_result() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--summary", "--where", "kind = 'view'", "--columns", "id"])
+        .write_stdin(input(6))
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "result[2]{id}:\n3\n6\n\nresult_stats[1]{column,type,nulls,distinct,min,max,mean,top,length}:\nid,integer,0,2,3,6,4.5,,1..1\n",
+        ));
+}
+
+#[test]
+fn test_summary_sample_size_and_seed() {
+    let run = |seed: &str| {
+        let mut cmd = cargo_bin_cmd!("tose_converter");
+        let output = cmd
+            .args(["--summary", "--sample-size", "2", "--seed", seed])
+            .write_stdin(input(100))
+            .output()
+            .unwrap();
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let first = run("7");
+    assert!(first.starts_with("result[2 of 100, sample=reservoir(seed=7)]{id,kind}:\n"));
+    assert_eq!(first, run("7"));
+}
+
+#[test]
+fn test_summary_rejects_stats() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--summary", "--stats"])
+        .write_stdin(input(3))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("not available in summary mode"));
+}
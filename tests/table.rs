@@ -0,0 +1,27 @@
+use tose_converter::{Table, ToseWriter};
+
+#[test]
+fn test_transform_parsed_table() {
+    let psql = "NOTICE:  view \"recent\" is stale\n id | status  | note\n----+---------+------\n  1 | active  |\n  2 | deleted | spam\n  3 | active  | vip\n(3 rows)\n";
+    let mut table = Table::parse(psql.as_bytes()).unwrap();
+    assert_eq!(table.source.footer_count, Some(3));
+    assert_eq!(table.source.notices, ["NOTICE:  view \"recent\" is stale"]);
+    assert_eq!(table.source.query, None);
+
+    // Drop deleted rows downstream of parsing, then write what is left
+    table
+        .rows
+        .retain(|row| row[1].as_deref() != Some("deleted"));
+    let columns: Vec<&str> = table.columns.iter().map(String::as_str).collect();
+    let mut writer =
+        ToseWriter::with_row_count(Vec::new(), "users", &columns, table.rows.len()).unwrap();
+    for row in &table.rows {
+        let cells: Vec<Option<&str>> = row.iter().map(Option::as_deref).collect();
+        writer.write_row(&cells).unwrap();
+    }
+    let output = String::from_utf8(writer.finish().unwrap()).unwrap();
+    assert_eq!(
+        output,
+        "users[2]{id,status,note}:\n1,active,\n3,active,vip\n"
+    );
+}
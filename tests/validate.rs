@@ -0,0 +1,83 @@
+use std::fs;
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+
+const INPUT: &str = " id | name  \n----+-------\n  1 | Alice\n  2 | Bob\n(2 rows)\n";
+
+#[test]
+fn test_validate_converter_output() {
+    let output = cargo_bin_cmd!("tose_converter")
+        .args(["--max-rows", "1", "--meta"])
+        .write_stdin(INPUT)
+        .output()
+        .unwrap();
+
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.arg("validate")
+        .write_stdin(output.stdout)
+        .assert()
+        .code(0)
+        .stdout(predicate::str::is_empty());
+}
+
+#[test]
+fn test_validate_folded_deduped_and_profiled_output() {
+    let input = " id | tenant | level \n----+--------+-------\n  1 | 7      | INFO\n  1 | 7      | INFO\n  2 | 7      | WARN\n(3 rows)\n";
+    let runs: [&[&str]; 3] = [
+        &["--fold-constants", "--dedupe"],
+        &["--profile"],
+        &["--dedupe", "--max-rows", "1"],
+    ];
+    for flags in runs {
+        let output = cargo_bin_cmd!("tose_converter")
+            .args(flags)
+            .write_stdin(input)
+            .output()
+            .unwrap();
+
+        let mut cmd = cargo_bin_cmd!("tose_converter");
+        cmd.arg("validate")
+            .write_stdin(output.stdout)
+            .assert()
+            .code(0)
+            .stdout(predicate::str::is_empty());
+    }
+}
+
+#[test]
+fn test_validate_reports_rule_and_line() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("users.tose");
+    fs::write(&path, "user-list[3]{id, name}:\n1,Alice\n2\n").unwrap();
+    let path = path.to_str().unwrap().to_string();
+
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["validate", &path])
+        .assert()
+        .code(1)
+        .stdout(format!(
+            "{path}:1: entity-name: column 5: invalid character '-' in entity name\n\
+         {path}:1: field-list: spaces around field name \" name\"\n\
+         {path}:1: row-count: header declares 3 records, found 2\n\
+         {path}:3: field-count: expected 2 fields, found 1\n"
+        ));
+}
+
+#[test]
+fn test_validate_missing_file() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["validate", "does-not-exist.tose"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::starts_with("does-not-exist.tose: "));
+}
+
+#[test]
+fn test_validate_lists_rules() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["validate", "--rules"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("row-count\t3.1\t"));
+}
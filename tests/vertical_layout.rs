@@ -0,0 +1,65 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use tose_converter::{RecordLayout, ToseConverter};
+
+const ONE_ROW: &str = " id | name  | email     | plan \n----+-------+-----------+------\n  1 | Alice | a@x.io    | pro\n(1 row)\n";
+
+const TWO_ROWS: &str = " id | name  \n----+-------\n  1 | Alice\n  2 | Bob\n(2 rows)\n";
+
+fn convert(converter: ToseConverter, input: &str) -> String {
+    let mut output = Vec::new();
+    converter.convert(input.as_bytes(), &mut output).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn test_vertical_single_row() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--layout", "vertical"])
+        .write_stdin(ONE_ROW)
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "result[1, vertical]{field,value}:\nid,1\nname,Alice\nemail,a@x.io\nplan,pro\n",
+        ));
+}
+
+#[test]
+fn test_vertical_thresholds() {
+    // Two rows exceed the default row threshold, so the table layout stays
+    let converter = ToseConverter::new().with_layout(RecordLayout::Vertical);
+    assert_eq!(convert(converter, TWO_ROWS), "result[2]{id,name}:\n1,Alice\n2,Bob\n");
+
+    let converter = ToseConverter::new()
+        .with_layout(RecordLayout::Vertical)
+        .with_vertical_max_rows(2);
+    assert_eq!(
+        convert(converter, TWO_ROWS),
+        "result[2, vertical]{field,value_1,value_2}:\nid,1,2\nname,Alice,Bob\n"
+    );
+
+    let converter = ToseConverter::new()
+        .with_layout(RecordLayout::Vertical)
+        .with_vertical_min_columns(2);
+    assert!(convert(converter, TWO_ROWS).starts_with("result[2, vertical]"));
+}
+
+#[test]
+fn test_auto_keeps_cheaper_table() {
+    // The transposed block repeats every column name, so it is never cheaper
+    // under the default chars/4 estimate; ties keep the table layout
+    for input in [ONE_ROW, TWO_ROWS] {
+        let auto = convert(ToseConverter::new().with_layout(RecordLayout::Auto), input);
+        assert_eq!(auto, convert(ToseConverter::new(), input));
+    }
+}
+
+#[test]
+fn test_unknown_layout() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--layout", "wide"])
+        .write_stdin(ONE_ROW)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Unknown layout: wide"));
+}
@@ -0,0 +1,134 @@
+use std::fs;
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use tose_converter::{ToseConverter, WrapTemplate};
+
+const INPUT: &str = " id | name  \n----+-------\n  1 | Alice\n  2 | Bob\n(2 rows)\n";
+
+#[test]
+fn test_wrap_fence() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--wrap", "fence"])
+        .write_stdin(INPUT)
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "```tose\nresult[2]{id,name}:\n1,Alice\n2,Bob\n```\n",
+        ));
+}
+
+#[test]
+fn test_wrap_xml() {
+    let converter = ToseConverter::new()
+        .with_entity_name("users")
+        .with_max_rows(1)
+        .with_wrap("xml".parse().unwrap());
+    let mut output = Vec::new();
+    converter.convert(INPUT.as_bytes(), &mut output).unwrap();
+
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "<data name=\"users\" rows=\"1\">\nusers[1 of 2]{id,name}:\n1,Alice\n# truncated: 1 more rows not shown\n</data>\n"
+    );
+}
+
+#[test]
+fn test_wrap_explain_with_query() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--wrap", "explain", "--query", "SELECT id, name FROM users"])
+        .write_stdin(INPUT)
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("The data below is in TOSE format."))
+        .stdout(predicate::str::contains(
+            "Query: SELECT id, name FROM users\n\n```tose\nresult[2]{id,name}:\n",
+        ));
+
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--wrap", "explain"])
+        .write_stdin(INPUT)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Query:").not());
+}
+
+#[test]
+fn test_wrap_template_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("prompt.txt");
+    fs::write(
+        &path,
+        "-- {query}\n{entity}: {rows} of {total} rows, columns {columns}\n{body}\n-- end\n",
+    )
+    .unwrap();
+
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.arg("--wrap")
+        .arg(&path)
+        .args(["--query", "SELECT 1", "--columns", "name"])
+        .write_stdin(INPUT)
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "-- SELECT 1\nresult: 2 of 2 rows, columns name\nresult[2]{name}:\nAlice\nBob\n-- end\n",
+        ));
+}
+
+#[test]
+fn test_wrap_document() {
+    let converter = ToseConverter::new().with_wrap(WrapTemplate::new("<{entity}>\n{body}\n"));
+    let mut output = Vec::new();
+    converter
+        .convert_document(
+            vec![
+                ("a".to_string(), INPUT.as_bytes()),
+                ("b".to_string(), INPUT.as_bytes()),
+            ],
+            &mut output,
+        )
+        .unwrap();
+
+    let result = String::from_utf8(output).unwrap();
+    assert!(result.starts_with("<a,b>\n@document[2]{a,b}:\n"));
+    assert_eq!(result.matches("[2]{id,name}:").count(), 2);
+}
+
+#[test]
+fn test_missing_template_file() {
+    let mut cmd = cargo_bin_cmd!("tose_converter");
+    cmd.args(["--wrap", "/nonexistent/template.txt"])
+        .write_stdin(INPUT)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Cannot read wrap template /nonexistent/template.txt"));
+}
+
+#[test]
+fn test_wrap_counts_against_max_tokens() {
+    let mut input = " id | name\n----+------\n".to_string();
+    for i in 1..=40 {
+        input.push_str(&format!(" {} | user{}\n", i, i));
+    }
+    input.push_str("(40 rows)\n");
+
+    let converter = ToseConverter::new()
+        .with_max_tokens(120)
+        .with_wrap("explain".parse().unwrap());
+    let mut output = Vec::new();
+    converter.convert(input.as_bytes(), &mut output).unwrap();
+
+    let result = String::from_utf8(output).unwrap();
+    assert!(result.contains("```tose\nresult["));
+    assert!(result.contains(" of 40]{id,name}:\n1,user1\n"));
+    assert!(result.chars().count().div_ceil(4) <= 120);
+
+    let converter = ToseConverter::new()
+        .with_max_tokens(60)
+        .with_wrap("explain".parse().unwrap());
+    let err = converter.convert(input.as_bytes(), &mut Vec::new()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "The --wrap template alone takes 85 tokens, over --max-tokens 60"
+    );
+}
@@ -0,0 +1,33 @@
+use tose_converter::{ToseReader, ToseWriter, validate};
+
+#[test]
+fn test_written_entity_reads_back() {
+    let rows = [
+        [Some("1"), Some("Smith, J."), Some("line one\nline two")],
+        [Some("2"), None, Some("")],
+    ];
+    let mut writer = ToseWriter::new(Vec::new(), "notes", &["id", "author", "body"]).unwrap();
+    for row in &rows {
+        writer.write_row(row).unwrap();
+    }
+    let output = writer.finish().unwrap();
+    let document = String::from_utf8(output).unwrap();
+    assert!(validate(&document).is_empty());
+
+    let reader = ToseReader::new(document.as_bytes()).unwrap();
+    assert_eq!(reader.header().entity, "notes");
+    let read: Vec<_> = reader.map(Result::unwrap).collect();
+    let expected: Vec<Vec<Option<String>>> = rows
+        .iter()
+        .map(|row| row.iter().map(|cell| cell.map(String::from)).collect())
+        .collect();
+    assert_eq!(read, expected);
+}
+
+#[test]
+fn test_invalid_entity_name() {
+    let err = ToseWriter::with_row_count(Vec::new(), "user list", &["id"], 0)
+        .err()
+        .unwrap();
+    assert!(err.to_string().starts_with("Invalid entity name"));
+}